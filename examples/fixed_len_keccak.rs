@@ -2,7 +2,7 @@ use axiom_eth::{keccak::KeccakChip, EthChip, Field};
 use clap::Parser;
 use ethers_core::utils::keccak256;
 use halo2_base::{gates::RangeInstructions, AssignedValue, Context};
-use halo2_scaffold::scaffold::{cmd::Cli, run_eth};
+use halo2_scaffold::scaffold::{cmd::Cli, run_eth, KeygenInputs};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -10,6 +10,13 @@ pub struct CircuitInput {
     pub bytes: Vec<u8>,
 }
 
+impl KeygenInputs for CircuitInput {
+    // the circuit depends on the number of bytes, so this creates the circuit for `LEN = 0`
+    fn keygen_inputs() -> Self {
+        Self { bytes: vec![] }
+    }
+}
+
 /// Input is a fixed number of bytes. THE CIRCUIT DEPENDS ON THE NUMBER OF BYTES.
 /// The bytes are range checked to be 8 bits each and exposed as public inputs.
 /// The 32 byte keccak hash of the input bytes are exposed as public outputs.
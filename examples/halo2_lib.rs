@@ -8,7 +8,7 @@ use halo2_base::{
     QuantumCell::{Constant, Existing, Witness},
 };
 use halo2_scaffold::scaffold::cmd::Cli;
-use halo2_scaffold::scaffold::run;
+use halo2_scaffold::scaffold::{run, KeygenInputs};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -16,6 +16,12 @@ pub struct CircuitInput {
     pub x: String, // field element, but easier to deserialize as a string
 }
 
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        Self { x: "0".to_string() }
+    }
+}
+
 // this algorithm takes a public input x, computes x^2 + 72, and outputs the result as public output
 fn some_algorithm_in_zk<F: ScalarField>(
     ctx: &mut Context<F>,
@@ -0,0 +1,37 @@
+//! Runs the mock prover on a hand-written standard PLONK program, see `src/circuits/plonk_program.rs` for the format.
+//!
+//! Usage: `cargo run --example plonk_program -- data/plonk_program.plonk x=3`
+//! where each `name=value` argument gives the value of an input variable, and the other variables are solved from the gates.
+use std::{env, fs};
+
+use halo2_proofs::{circuit::Value, dev::MockProver, halo2curves::bn256::Fr};
+use halo2_scaffold::circuits::plonk_program::{StandardPlonkProgram, StandardPlonkProgramCircuit};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args.next().expect("usage: plonk_program <program> [name=value]...");
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| panic!("Could not read {path}: {e}"));
+    let program = StandardPlonkProgram::<Fr>::parse(&text)
+        .unwrap_or_else(|e| panic!("Invalid program {path}: {e}"));
+
+    let inputs = args
+        .map(|arg| {
+            let (name, value) =
+                arg.split_once('=').unwrap_or_else(|| panic!("expected name=value, found {arg}"));
+            let value: u64 = value.parse().unwrap_or_else(|e| panic!("invalid value {value}: {e}"));
+            (name.to_string(), Fr::from(value))
+        })
+        .collect::<Vec<_>>();
+    let inputs = inputs.iter().map(|(name, value)| (name.as_str(), *value)).collect::<Vec<_>>();
+    let witness = program.solve(&inputs).unwrap_or_else(|e| panic!("{e}"));
+
+    let instances = program.instances(&witness);
+    for (var, value) in program.public.iter().zip(&instances) {
+        println!("{} = {:?}", program.variables[*var], value);
+    }
+
+    let k = program.min_k();
+    let circuit = StandardPlonkProgramCircuit { program, witness: Value::known(witness) };
+    MockProver::run(k, &circuit, vec![instances]).unwrap().assert_satisfied();
+    println!("Mock prover satisfied with k = {k}");
+}
@@ -1,6 +1,6 @@
 use clap::Parser;
 use halo2_base::{gates::GateChip, utils::ScalarField, AssignedValue, Context};
-use halo2_scaffold::scaffold::{cmd::Cli, run};
+use halo2_scaffold::scaffold::{cmd::Cli, run, KeygenInputs};
 use poseidon::PoseidonChip;
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +14,12 @@ pub struct CircuitInput {
     pub inputs: [String; 2], // two field elements, but as strings for easier deserialization
 }
 
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        Self { inputs: ["0".to_string(), "0".to_string()] }
+    }
+}
+
 fn hash_two<F: ScalarField>(
     ctx: &mut Context<F>,
     inp: CircuitInput,
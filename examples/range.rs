@@ -3,15 +3,20 @@ use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
 use halo2_base::utils::ScalarField;
 use halo2_base::{AssignedValue, Context};
 use halo2_scaffold::scaffold::cmd::Cli;
-use halo2_scaffold::scaffold::run;
+use halo2_scaffold::scaffold::{lookup_bits, run, KeygenInputs};
 use serde::{Deserialize, Serialize};
-use std::env::var;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitInput {
     pub x: String, // field element, but easier to deserialize as a string
 }
 
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        Self { x: "0".to_string() }
+    }
+}
+
 fn some_algorithm_in_zk<F: ScalarField>(
     ctx: &mut Context<F>,
     input: CircuitInput,
@@ -21,9 +26,9 @@ fn some_algorithm_in_zk<F: ScalarField>(
     // `Context` can roughly be thought of as a single-threaded execution trace of a program we want to ZK prove. We do some post-processing on `Context` to optimally divide the execution trace into multiple columns in a PLONKish arithmetization
     // More advanced usage with multi-threaded witness generation is possible, but we do not explain it here
 
-    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
-    let lookup_bits =
-        var("LOOKUP_BITS").unwrap_or_else(|_| panic!("LOOKUP_BITS not set")).parse().unwrap();
+    // lookup bits must agree with the size of the lookup table, which is specified by the `--lookup-bits` command line option
+    let lookup_bits = lookup_bits();
+    assert!(lookup_bits > 0, "--lookup-bits not set");
     // first we load a private input `x`
     let x = ctx.load_witness(x);
     // make it public
@@ -0,0 +1,63 @@
+use axiom_eth::{rlp::RlpChip, Field};
+use clap::Parser;
+use halo2_base::{gates::GateInstructions, AssignedValue, Context};
+use halo2_scaffold::scaffold::{cmd::Cli, run_rlc, KeygenInputs};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<String>, // field elements, but easier to deserialize as strings
+    pub b: Vec<String>,
+    pub c: Vec<String>,
+}
+
+impl KeygenInputs for CircuitInput {
+    // the circuit depends on the lengths of the arrays, so this creates the circuit for the lengths of `data/rlc_concat.in`
+    fn keygen_inputs() -> Self {
+        let zeros = |len| vec!["0".to_string(); len];
+        Self { a: zeros(3), b: zeros(2), c: zeros(5) }
+    }
+}
+
+/// Proves that the public array `c` is the concatenation of the private arrays `a` and `b`. THE CIRCUIT DEPENDS ON THE LENGTHS OF THE ARRAYS.
+///
+/// Instead of comparing `c` element by element, we compare random linear combinations (RLC): with `rlc(x) = x_0 * gamma^{n-1} + ... + x_{n-1}`,
+/// `c = a ++ b` implies `rlc(c) = rlc(a) * gamma^len(b) + rlc(b)`, and the converse holds with high probability because `gamma` is a random challenge
+/// chosen after `a`, `b`, `c` are committed. The same technique is used for permutation and multiset arguments.
+fn rlc_concat<F: Field>(
+    ctx: &mut Context<F>,
+    _rlp: &RlpChip<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> impl FnOnce(&mut Context<F>, &mut Context<F>, &RlpChip<F>) + Clone {
+    let [a, b, c] = [input.a, input.b, input.c].map(|values| {
+        ctx.assign_witnesses(
+            values.iter().map(|x| F::from_str_vartime(x).expect("deserialize field element")),
+        )
+    });
+    assert_eq!(a.len() + b.len(), c.len(), "c must have the length of a and b combined");
+    make_public.extend(c.iter().copied());
+
+    // The challenge `gamma` is only available in SecondPhase, so everything using it happens in this callback
+    move |ctx_gate: &mut Context<F>, ctx_rlc: &mut Context<F>, rlp: &RlpChip<F>| {
+        let rlc = rlp.rlc();
+        let gate = rlp.gate();
+        let len_b = b.len();
+        let [rlc_a, rlc_b, rlc_c] =
+            [a, b, c].map(|values| rlc.compute_rlc_fixed_len(ctx_rlc, values).rlc_val);
+        // caches gamma^(2^i) so that gamma^len_b can be computed
+        let cache_bits = usize::BITS as usize - len_b.leading_zeros() as usize;
+        rlc.load_rlc_cache((&mut *ctx_gate, &mut *ctx_rlc), gate, cache_bits);
+        let gamma_pow = rlc.rlc_pow_fixed(ctx_gate, gate, len_b);
+        let concat = gate.mul_add(ctx_gate, rlc_a, gamma_pow, rlc_b);
+        ctx_gate.constrain_equal(&concat, &rlc_c);
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+    // use run_rlc instead of run to get access to the RlcChip in SecondPhase
+    run_rlc(rlc_concat, args);
+}
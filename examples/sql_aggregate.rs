@@ -0,0 +1,77 @@
+use clap::Parser;
+use halo2_base::{
+    gates::{RangeChip, RangeInstructions},
+    halo2_proofs::halo2curves::bn256::Fr,
+    AssignedValue, Context,
+};
+use halo2_scaffold::{
+    scaffold::{cmd::Cli, lookup_bits, run, KeygenInputs},
+    sql::{
+        aggregate::{aggregate, Aggregate, Avg, Function},
+        query::{Comparison, Predicate},
+        table::{commit_table, set_lookup_bits, Cell, Column, ColumnType, Schema, Table, R_F, R_P},
+    },
+};
+use poseidon::PoseidonChip;
+use serde::Deserialize;
+
+// The circuit depends on the schema and the number of rows of the table, and on the function, column and comparison of the query.
+// Keys generated without an input file are for the table and query in `data/sql_aggregate.in`, with any constant.
+const NUM_ROWS: usize = 8;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CircuitInput {
+    pub table: Table<Fr>,
+    pub query: Aggregate,
+}
+
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        let column = |name: &str, ty| Column { name: name.to_string(), ty };
+        let schema = Schema {
+            columns: vec![
+                column("id", ColumnType::Uint { bits: 32 }),
+                column("customer", ColumnType::Text),
+                column("amount", ColumnType::Uint { bits: 64 }),
+                column("paid", ColumnType::Bool),
+            ],
+        };
+        let filter = Predicate {
+            column: "paid".to_string(),
+            comparison: Comparison::Eq { value: Cell::Bool(false) },
+        };
+        let query = Aggregate {
+            function: Function::Avg,
+            column: Some("amount".to_string()),
+            filter: Some(filter),
+            avg: Avg::Rounded,
+        };
+        Self { table: Table::placeholder(schema, NUM_ROWS), query }
+    }
+}
+
+fn aggregate_where(
+    ctx: &mut Context<Fr>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<Fr>>,
+) {
+    let range = RangeChip::default(lookup_bits());
+    let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+    // public instances: the table commitment, the constants of the `WHERE` clause, the number of matching rows, then the value of the function
+    let table = commit_table(ctx, range.gate(), &poseidon, &input.table, make_public);
+    let result = aggregate(ctx, &range, &table, &input.query, make_public)
+        .unwrap_or_else(|e| panic!("invalid query: {e}"));
+    println!("matching rows: {:?}", result.count.value());
+    if let Some(value) = result.value {
+        println!("{:?}: {:?}", input.query.function, value.value());
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse();
+    // unless `--lookup-bits` is given, the lookup table is sized for the widest column of the table
+    set_lookup_bits(&mut args, |input: &CircuitInput| &input.table.schema);
+    run(aggregate_where, args);
+}
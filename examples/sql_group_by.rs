@@ -0,0 +1,79 @@
+use axiom_eth::rlp::RlpChip;
+use clap::Parser;
+use halo2_base::{
+    gates::RangeInstructions, halo2_proofs::halo2curves::bn256::Fr, AssignedValue, Context,
+};
+use halo2_scaffold::{
+    scaffold::{cmd::Cli, run_rlc, KeygenInputs},
+    sql::{
+        group_by::{group_by, GroupBy},
+        table::{commit_table, set_lookup_bits, Column, ColumnType, Schema, Table, R_F, R_P},
+    },
+};
+use poseidon::PoseidonChip;
+use serde::Deserialize;
+
+// The circuit depends on the schema and the number of rows of the table, and on the columns and `max_groups` of the query.
+// Keys generated without an input file are for the table and query in `data/sql_group_by.in`.
+const NUM_ROWS: usize = 8;
+const MAX_GROUPS: usize = 4;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CircuitInput {
+    pub table: Table<Fr>,
+    pub query: GroupBy,
+}
+
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        let column = |name: &str, ty| Column { name: name.to_string(), ty };
+        let schema = Schema {
+            columns: vec![
+                column("id", ColumnType::Uint { bits: 32 }),
+                column("customer_id", ColumnType::Uint { bits: 32 }),
+                column("amount", ColumnType::Uint { bits: 64 }),
+                column("paid", ColumnType::Bool),
+            ],
+        };
+        let query = GroupBy {
+            key: "customer_id".to_string(),
+            value: "amount".to_string(),
+            max_groups: MAX_GROUPS,
+        };
+        Self { table: Table::placeholder(schema, NUM_ROWS), query }
+    }
+}
+
+/// Proves `SELECT customer_id, COUNT(*), SUM(amount) GROUP BY customer_id`. The groups are computed from the rows sorted by key in phase 0,
+/// and the returned callback proves in phase 1, with the random challenge, that the sorted rows are the rows of the table.
+fn group_by_key(
+    ctx: &mut Context<Fr>,
+    rlp: &RlpChip<Fr>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<Fr>>,
+) -> impl FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone {
+    let range = rlp.range();
+    let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+    // public instances: the table commitment, the number of groups, then `[key, count, sum]` for each of the `MAX_GROUPS` slots
+    let table = commit_table(ctx, range.gate(), &poseidon, &input.table, make_public);
+    let result = group_by(ctx, range, &table, &input.query, make_public)
+        .unwrap_or_else(|e| panic!("invalid query: {e}"));
+    println!("groups: {:?}", result.num_groups.value());
+    for [key, count, sum] in &result.groups {
+        println!("{:?}: count {:?}, sum {:?}", key.value(), count.value(), sum.value());
+    }
+
+    let permutation = result.permutation;
+    move |ctx_gate: &mut Context<Fr>, ctx_rlc: &mut Context<Fr>, rlp: &RlpChip<Fr>| {
+        permutation.constrain(ctx_gate, ctx_rlc, rlp.gate(), rlp.rlc());
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse();
+    // unless `--lookup-bits` is given, the lookup table is sized for the widest column of the table
+    set_lookup_bits(&mut args, |input: &CircuitInput| &input.table.schema);
+    run_rlc(group_by_key, args);
+}
@@ -0,0 +1,74 @@
+use clap::Parser;
+use halo2_base::{
+    gates::{RangeChip, RangeInstructions},
+    halo2_proofs::halo2curves::bn256::Fr,
+    AssignedValue, Context,
+};
+use halo2_scaffold::{
+    scaffold::{cmd::Cli, lookup_bits, run, KeygenInputs},
+    sql::{
+        query::{select, Comparison, Predicate, Select},
+        table::{commit_table, set_lookup_bits, Cell, Column, ColumnType, Schema, Table, R_F, R_P},
+    },
+};
+use poseidon::PoseidonChip;
+use serde::Deserialize;
+
+// The circuit depends on the schema and the number of rows of the table, and on the columns and comparison of the query.
+// Keys generated without an input file are for the table and query in `data/sql_select.in`, with any constant.
+// `data/sql_select_range.in` has a `BETWEEN` query instead, which needs its own keys.
+const NUM_ROWS: usize = 8;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CircuitInput {
+    pub table: Table<Fr>,
+    pub query: Select,
+}
+
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        let column = |name: &str, ty| Column { name: name.to_string(), ty };
+        let schema = Schema {
+            columns: vec![
+                column("id", ColumnType::Uint { bits: 32 }),
+                column("customer", ColumnType::Text),
+                column("amount", ColumnType::Uint { bits: 64 }),
+                column("paid", ColumnType::Bool),
+            ],
+        };
+        let filter = Predicate {
+            column: "customer".to_string(),
+            comparison: Comparison::Eq { value: Cell::String(String::new()) },
+        };
+        let query = Select {
+            columns: vec!["id".to_string(), "amount".to_string()],
+            filter: Some(filter),
+            ..Default::default()
+        };
+        Self { table: Table::placeholder(schema, NUM_ROWS), query }
+    }
+}
+
+fn select_where(
+    ctx: &mut Context<Fr>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<Fr>>,
+) {
+    // ordered comparisons use the lookup table for range checks
+    let range = RangeChip::default(lookup_bits());
+    let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+    // public instances: the table commitment, the constants of the `WHERE` clause, the number of matching rows, then the rows padded with zeros
+    let table = commit_table(ctx, range.gate(), &poseidon, &input.table, make_public);
+    let result = select(ctx, &range, &poseidon, &table, &input.query, make_public)
+        .unwrap_or_else(|e| panic!("invalid query: {e}"));
+    println!("matching rows: {:?}", result.count.value());
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse();
+    // unless `--lookup-bits` is given, the lookup table is sized for the widest column of the table
+    set_lookup_bits(&mut args, |input: &CircuitInput| &input.table.schema);
+    run(select_where, args);
+}
@@ -0,0 +1,47 @@
+use clap::Parser;
+use halo2_base::{gates::GateChip, halo2_proofs::halo2curves::bn256::Fr, AssignedValue, Context};
+use halo2_scaffold::{
+    scaffold::{cmd::Cli, run, KeygenInputs},
+    sql::table::{commit_table, Column, ColumnType, Schema, Table, R_F, R_P},
+};
+use poseidon::PoseidonChip;
+use serde::Deserialize;
+
+// The circuit depends on the schema and the number of rows of the table, so keys generated without an input file are for tables like `data/sql_table.in`
+const NUM_ROWS: usize = 8;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct CircuitInput {
+    pub table: Table<Fr>,
+}
+
+impl KeygenInputs for CircuitInput {
+    fn keygen_inputs() -> Self {
+        let column = |name: &str, ty| Column { name: name.to_string(), ty };
+        let schema = Schema {
+            columns: vec![
+                column("id", ColumnType::Uint { bits: 32 }),
+                column("customer", ColumnType::Text),
+                column("amount", ColumnType::Uint { bits: 64 }),
+                column("paid", ColumnType::Bool),
+            ],
+        };
+        Self { table: Table::placeholder(schema, NUM_ROWS) }
+    }
+}
+
+fn commit(ctx: &mut Context<Fr>, input: CircuitInput, make_public: &mut Vec<AssignedValue<Fr>>) {
+    let gate = GateChip::default();
+    let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+    // the commitment is the only public instance, and should equal the one published by the data owner with `cargo run --bin commit_table`
+    let table = commit_table(ctx, &gate, &poseidon, &input.table, make_public);
+    println!("table commitment: {:?}", table.commitment.value());
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+    run(commit, args);
+}
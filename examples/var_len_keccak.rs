@@ -2,7 +2,7 @@ use axiom_eth::{keccak::KeccakChip, EthChip, Field};
 use clap::Parser;
 use ethers_core::utils::keccak256;
 use halo2_base::{gates::RangeInstructions, AssignedValue, Context};
-use halo2_scaffold::scaffold::{cmd::Cli, run_eth};
+use halo2_scaffold::scaffold::{cmd::Cli, run_eth, KeygenInputs};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -11,6 +11,13 @@ pub struct CircuitInput {
     pub len: usize,            // the variable length of the input bytes
 }
 
+impl KeygenInputs for CircuitInput {
+    // the circuit depends on `MAX_LEN`, so this creates the circuit for `MAX_LEN = 3`
+    fn keygen_inputs() -> Self {
+        Self { padded_bytes: vec![0; 3], len: 0 }
+    }
+}
+
 /// Variable length byte arrays are expressed as a fixed length byte array of length `MAX_LEN`, right padded with arbitrary bytes (typically 0s), together with the actual length of the input bytes.
 /// The bytes (including padding bytes) are range checked to be 8 bits each and exposed as public inputs.
 /// The 32 byte keccak hash of `padded_bytes[..len]` are exposed as public outputs.
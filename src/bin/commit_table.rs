@@ -0,0 +1,22 @@
+//! Prints the Poseidon commitment of a table, which the data owner publishes so that anyone can check it against the commitment exposed by a query circuit.
+//!
+//! Usage: `cargo run --bin commit_table -- <TABLE_FILE>`, e.g. `cargo run --bin commit_table -- data/sql_table.in`
+use std::path::PathBuf;
+
+use clap::Parser;
+use halo2_base::{halo2_proofs::halo2curves::bn256::Fr, utils::fe_to_biguint};
+use halo2_scaffold::{scaffold::exit_on_error, sql::table::Table};
+
+#[derive(Parser, Debug)]
+struct CommitCli {
+    /// JSON file with the schema and rows of the table
+    table: PathBuf,
+}
+
+fn main() {
+    let args = CommitCli::parse();
+    let table = exit_on_error(Table::<Fr>::read(&args.table));
+    let commitment = fe_to_biguint(&table.commitment());
+    println!("{commitment}");
+    println!("0x{}", commitment.to_str_radix(16));
+}
@@ -0,0 +1,14 @@
+//! Verifies a snark created by the scaffold using only the verifying key, the circuit pinning, and the snark with its metadata file.
+//! The metadata file `{name}.snark.json` is required unless the snark uses the default Poseidon transcript with SHPLONK.
+//! Neither the circuit nor its private inputs are needed, so this can be run by third parties.
+//!
+//! Usage: `cargo run --bin verify -- --name <NAME> [--kind range|eth|rlc|aggregation] [--params <SRS_FILE>] [--expect-instances <FILE>]`
+use clap::Parser;
+use halo2_scaffold::scaffold::{cmd::VerifyCli, exit_on_error, verifier::verify_cli};
+
+fn main() {
+    env_logger::init();
+
+    let args = VerifyCli::parse();
+    exit_on_error(verify_cli(args));
+}
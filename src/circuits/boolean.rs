@@ -0,0 +1,395 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Region, Value},
+    halo2curves::FieldExt,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+// This generalizes the OR gate of `or.rs` to a chip for arbitrary boolean logic, using the same single witness column:
+// | witness | q_and | q_or | q_xor | q_not | q_bool |
+// | a       | 1     |      |       |       |        |
+// | b       |       |      |       |       |        |
+// | out     |       |      |       |       |        |
+// with one gate per operation, each applied where its selector is on:
+// AND:  q_and * (a * b - out) = 0
+// OR:   q_or  * (a + b - a * b - out) = 0
+// XOR:  q_xor * (a + b - 2 * a * b - out) = 0
+// NOT:  q_not * (1 - a - out) = 0, where `out` is in the row right after `a`
+// BOOL: q_bool * x * (x - 1) = 0, which forces x to be 0 or 1
+// Unlike `or.rs`, booleanity of the inputs is an actual constraint instead of a native `assert_eq!`.
+// The output of each gate is boolean whenever its inputs are, so only the inputs need the BOOL gate.
+
+/// The columns and gates of a [`BoolChip`].
+#[derive(Clone, Copy, Debug)]
+pub struct BoolConfig {
+    pub witness: Column<Advice>,
+    q_bool: Selector,
+    q_and: Selector,
+    q_or: Selector,
+    q_xor: Selector,
+    q_not: Selector,
+}
+
+impl BoolConfig {
+    pub fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+        let witness = meta.advice_column();
+        meta.enable_equality(witness);
+        let [q_bool, q_and, q_or, q_xor, q_not] = [(); 5].map(|_| meta.selector());
+
+        let binary_gate =
+            |meta: &mut ConstraintSystem<F>,
+             name: &'static str,
+             selector: Selector,
+             out_expr: fn(Expression<F>, Expression<F>) -> Expression<F>| {
+                meta.create_gate(name, |meta| {
+                    let a = meta.query_advice(witness, Rotation::cur());
+                    let b = meta.query_advice(witness, Rotation(1));
+                    let out = meta.query_advice(witness, Rotation(2));
+                    let s = meta.query_selector(selector);
+                    vec![s * (out_expr(a, b) - out)]
+                });
+            };
+        binary_gate(meta, "AND gate", q_and, |a, b| a * b);
+        binary_gate(meta, "OR gate", q_or, |a, b| a.clone() + b.clone() - a * b);
+        binary_gate(meta, "XOR gate", q_xor, |a, b| {
+            a.clone() + b.clone() - Expression::Constant(F::from(2)) * a * b
+        });
+
+        meta.create_gate("NOT gate", |meta| {
+            let a = meta.query_advice(witness, Rotation::cur());
+            let out = meta.query_advice(witness, Rotation::next());
+            let s = meta.query_selector(q_not);
+            vec![s * (Expression::Constant(F::one()) - a - out)]
+        });
+
+        meta.create_gate("BOOL gate", |meta| {
+            let x = meta.query_advice(witness, Rotation::cur());
+            let s = meta.query_selector(q_bool);
+            vec![s * x.clone() * (x - Expression::Constant(F::one()))]
+        });
+
+        Self { witness, q_bool, q_and, q_or, q_xor, q_not }
+    }
+}
+
+/// A boolean expression over the bits loaded with [`BoolChip::load_bits`], referred to by their index.
+#[derive(Clone, Debug)]
+pub enum BoolExpr {
+    Input(usize),
+    Not(Box<BoolExpr>),
+    And(Box<BoolExpr>, Box<BoolExpr>),
+    Or(Box<BoolExpr>, Box<BoolExpr>),
+    Xor(Box<BoolExpr>, Box<BoolExpr>),
+    Nand(Box<BoolExpr>, Box<BoolExpr>),
+}
+
+impl BoolExpr {
+    pub fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+
+    pub fn and(self, other: Self) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Self) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn xor(self, other: Self) -> Self {
+        Self::Xor(Box::new(self), Box::new(other))
+    }
+
+    pub fn nand(self, other: Self) -> Self {
+        Self::Nand(Box::new(self), Box::new(other))
+    }
+
+    /// Evaluates the expression natively, outside of any circuit.
+    pub fn eval(&self, inputs: &[bool]) -> bool {
+        match self {
+            Self::Input(i) => inputs[*i],
+            Self::Not(a) => !a.eval(inputs),
+            Self::And(a, b) => a.eval(inputs) && b.eval(inputs),
+            Self::Or(a, b) => a.eval(inputs) || b.eval(inputs),
+            Self::Xor(a, b) => a.eval(inputs) ^ b.eval(inputs),
+            Self::Nand(a, b) => !(a.eval(inputs) && b.eval(inputs)),
+        }
+    }
+}
+
+/// Evaluates boolean logic in a single region, filled from the row `offset` downwards.
+///
+/// Consecutive gates share rotations: when the first input of a gate is the output of the previous gate, it is not copied again,
+/// but the gate starts at the row of that output.
+#[derive(Clone, Debug)]
+pub struct BoolChip<F: FieldExt> {
+    config: BoolConfig,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<F: FieldExt> BoolChip<F> {
+    pub fn construct(config: BoolConfig) -> Self {
+        Self { config, _marker: std::marker::PhantomData }
+    }
+
+    /// Assigns `bits` to consecutive rows starting at `offset` and constrains each of them to be 0 or 1.
+    pub fn load_bits(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        bits: &[Value<F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        bits.iter()
+            .map(|bit| {
+                let cell = region.assign_advice(|| "bit", self.config.witness, *offset, || *bit)?;
+                self.config.q_bool.enable(region, *offset)?;
+                *offset += 1;
+                Ok(cell)
+            })
+            .collect()
+    }
+
+    pub fn and(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.binary(region, offset, self.config.q_and, (a, false), b, |a, b| a * b)
+    }
+
+    pub fn or(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.binary(region, offset, self.config.q_or, (a, false), b, |a, b| a + b - a * b)
+    }
+
+    pub fn xor(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.binary(region, offset, self.config.q_xor, (a, false), b, xor)
+    }
+
+    pub fn not(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        a: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.unary_not(region, offset, (a, false))
+    }
+
+    /// NAND is an AND followed by a NOT, sharing the row of the intermediate output.
+    pub fn nand(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let and = self.and(region, offset, a, b)?;
+        self.unary_not(region, offset, (&and, true))
+    }
+
+    /// Evaluates `expr` over `inputs`, the cells returned by [`Self::load_bits`], and returns the output cell.
+    pub fn evaluate(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        expr: &BoolExpr,
+        inputs: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.evaluate_inner(region, offset, expr, inputs).map(|(out, _)| out)
+    }
+
+    /// Returns the output cell, and whether it is in the last assigned row, so that the next gate can start there.
+    fn evaluate_inner(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        expr: &BoolExpr,
+        inputs: &[AssignedCell<F, F>],
+    ) -> Result<(AssignedCell<F, F>, bool), Error> {
+        let (a, b, selector, op): (_, _, _, fn(F, F) -> F) = match expr {
+            BoolExpr::Input(i) => return Ok((inputs[*i].clone(), false)),
+            BoolExpr::Not(a) => {
+                let a = self.evaluate_inner(region, offset, a, inputs)?;
+                return Ok((self.unary_not(region, offset, (&a.0, a.1))?, true));
+            }
+            BoolExpr::Nand(a, b) => {
+                let and = BoolExpr::And(a.clone(), b.clone());
+                let and = self.evaluate_inner(region, offset, &and, inputs)?;
+                return Ok((self.unary_not(region, offset, (&and.0, and.1))?, true));
+            }
+            BoolExpr::And(a, b) => (a, b, self.config.q_and, |a, b| a * b),
+            BoolExpr::Or(a, b) => (a, b, self.config.q_or, |a, b| a + b - a * b),
+            BoolExpr::Xor(a, b) => (a, b, self.config.q_xor, xor),
+        };
+        // the second input is evaluated first, so that the first input ends up in the last row and can be shared with this gate
+        let (b, _) = self.evaluate_inner(region, offset, b, inputs)?;
+        let a = self.evaluate_inner(region, offset, a, inputs)?;
+        let out = self.binary(region, offset, selector, (&a.0, a.1), &b, op)?;
+        Ok((out, true))
+    }
+
+    /// Assigns `a`, `b`, `op(a, b)` to three consecutive rows and enables `selector` on the first one.
+    /// If `a_is_last`, `a` is already in the row before `offset` and is not copied again.
+    fn binary(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        selector: Selector,
+        (a, a_is_last): (&AssignedCell<F, F>, bool),
+        b: &AssignedCell<F, F>,
+        op: impl Fn(F, F) -> F,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let witness = self.config.witness;
+        let start = if a_is_last {
+            *offset - 1
+        } else {
+            a.copy_advice(|| "a", region, witness, *offset)?;
+            *offset
+        };
+        b.copy_advice(|| "b", region, witness, start + 1)?;
+        let out_val = a.value().zip(b.value()).map(|(a, b)| op(*a, *b));
+        let out = region.assign_advice(|| "out", witness, start + 2, || out_val)?;
+        selector.enable(region, start)?;
+        *offset = start + 3;
+        Ok(out)
+    }
+
+    /// Assigns `a`, `1 - a` to two consecutive rows, sharing the row of `a` if `a_is_last`.
+    fn unary_not(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        (a, a_is_last): (&AssignedCell<F, F>, bool),
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let witness = self.config.witness;
+        let start = if a_is_last {
+            *offset - 1
+        } else {
+            a.copy_advice(|| "a", region, witness, *offset)?;
+            *offset
+        };
+        let out_val = a.value().map(|a| F::one() - a);
+        let out = region.assign_advice(|| "not out", witness, start + 1, || out_val)?;
+        self.config.q_not.enable(region, start)?;
+        *offset = start + 2;
+        Ok(out)
+    }
+}
+
+fn xor<F: FieldExt>(a: F, b: F) -> F {
+    a + b - F::from(2) * a * b
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    use super::{BoolChip, BoolConfig, BoolExpr};
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
+
+    struct BoolTestCircuit {
+        bits: Vec<Fr>,
+        expr: BoolExpr,
+        // the value the output is checked against, computed natively with `BoolExpr::eval`
+        expected: Option<bool>,
+    }
+
+    impl Circuit<Fr> for BoolTestCircuit {
+        type Config = BoolConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            BoolConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = BoolChip::construct(config);
+            layouter.assign_region(
+                || "boolean expression",
+                |mut region| {
+                    let mut offset = 0;
+                    let bits = self.bits.iter().map(|bit| Value::known(*bit)).collect::<Vec<_>>();
+                    let inputs = chip.load_bits(&mut region, &mut offset, &bits)?;
+                    let out = chip.evaluate(&mut region, &mut offset, &self.expr, &inputs)?;
+                    if let Some(expected) = self.expected {
+                        out.value().assert_if_known(|out| **out == Fr::from(expected as u64));
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn test_expr() -> BoolExpr {
+        let [a, b, c] = [0, 1, 2].map(BoolExpr::Input);
+        // (a AND b) XOR ((NOT c) OR a), NAND c
+        a.clone().and(b).xor(c.clone().not().or(a)).nand(c)
+    }
+
+    #[test]
+    fn test_bool_expr() {
+        let k = 6;
+        for i in 0..8u64 {
+            let bits = [0, 1, 2].map(|j| (i >> j) & 1);
+            let expected = test_expr().eval(&bits.map(|bit| bit == 1));
+            let circuit = BoolTestCircuit {
+                bits: bits.iter().map(|bit| Fr::from(*bit)).collect(),
+                expr: test_expr(),
+                expected: Some(expected),
+            };
+            MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_bool_expr_tampered() {
+        let k = 6;
+        let circuit = BoolTestCircuit {
+            bits: vec![Fr::one(), Fr::zero(), Fr::one()],
+            expr: test_expr(),
+            expected: None,
+        };
+        // `Flip` negates the output and every intermediate result
+        for tamper in [Tamper::EachCell, Tamper::Flip] {
+            tamper_circuit(k, &circuit, vec![], tamper).assert_sound();
+        }
+    }
+
+    #[test]
+    fn test_bool_not_boolean() {
+        let k = 6;
+        // 2 OR 0 = 2 - 0 = 2 satisfies the OR gate, but 2 is not a bit
+        let circuit = BoolTestCircuit {
+            bits: vec![Fr::from(2), Fr::zero()],
+            expr: BoolExpr::Input(0).or(BoolExpr::Input(1)),
+            expected: None,
+        };
+        assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+    }
+}
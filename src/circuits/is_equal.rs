@@ -0,0 +1,143 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Region, Value},
+    halo2curves::FieldExt,
+    plonk::{ConstraintSystem, Error, Expression, VirtualCells},
+};
+
+use super::is_zero::{IsZeroChip, IsZeroChipConfig};
+
+// a == b exactly when a - b == 0, so checking equality is just an `IsZeroChip` on the expression `lhs - rhs`.
+// No new columns or gates are needed beyond those of the `IsZeroChip`.
+
+/// Checks whether two expressions are equal. The output is 1 if they are equal and 0 otherwise.
+#[derive(Clone, Debug)]
+pub struct IsEqualChip<F: FieldExt> {
+    is_zero: IsZeroChip<F>,
+}
+
+impl<F: FieldExt> IsEqualChip<F> {
+    pub fn construct(config: IsZeroChipConfig<F>) -> Self {
+        Self { is_zero: IsZeroChip::construct(config) }
+    }
+
+    /// `q_enable` selects the rows the check applies to, and `lhs` and `rhs` are the expressions that are compared, all queried at the current row.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        lhs: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        rhs: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+    ) -> IsZeroChipConfig<F> {
+        IsZeroChip::configure(meta, q_enable, |meta| lhs(meta) - rhs(meta))
+    }
+
+    /// Assigns the witness of the check at row `offset` of `region`, where `lhs` and `rhs` are the values of the compared expressions at that row.
+    /// Returns the output cell, which is 1 if `lhs == rhs` and 0 otherwise.
+    pub fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        lhs: Value<F>,
+        rhs: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.is_zero.assign(region, offset, lhs - rhs)
+    }
+
+    /// The output at the current row, for use in other gates.
+    pub fn expr(&self) -> Expression<F> {
+        self.is_zero.expr()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Selector},
+        poly::Rotation,
+    };
+    use rand::rngs::OsRng;
+
+    use super::{IsEqualChip, IsZeroChipConfig};
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
+
+    #[derive(Clone, Debug)]
+    struct IsEqualTestConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        q_enable: Selector,
+        is_equal: IsZeroChipConfig<Fr>,
+    }
+
+    struct IsEqualTestCircuit {
+        pairs: Vec<(Fr, Fr)>,
+    }
+
+    impl Circuit<Fr> for IsEqualTestCircuit {
+        type Config = IsEqualTestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let [a, b] = [(); 2].map(|_| meta.advice_column());
+            let q_enable = meta.selector();
+            let is_equal = IsEqualChip::configure(
+                meta,
+                |meta| meta.query_selector(q_enable),
+                |meta| meta.query_advice(a, Rotation::cur()),
+                |meta| meta.query_advice(b, Rotation::cur()),
+            );
+            IsEqualTestConfig { a, b, q_enable, is_equal }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = IsEqualChip::construct(config.is_equal.clone());
+            layouter.assign_region(
+                || "is_equal chip",
+                |mut region| {
+                    for (offset, (a, b)) in self.pairs.iter().enumerate() {
+                        config.q_enable.enable(&mut region, offset)?;
+                        let [a, b] = [a, b].map(|x| Value::known(*x));
+                        region.assign_advice(|| "a", config.a, offset, || a)?;
+                        region.assign_advice(|| "b", config.b, offset, || b)?;
+                        chip.assign(&mut region, offset, a, b)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_is_equal() {
+        let k = 5;
+        let x = Fr::random(OsRng);
+        let pairs = vec![(x, x), (x, x + Fr::one()), (Fr::zero(), Fr::zero()), (Fr::one(), x)];
+        let circuit = IsEqualTestCircuit { pairs };
+
+        MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_is_equal_tampered() {
+        let k = 5;
+        let x = Fr::random(OsRng);
+        let circuit =
+            IsEqualTestCircuit { pairs: vec![(x, x), (x, x + Fr::one()), (Fr::one(), x)] };
+        // `Flip` negates the output of the chip, and only the inverse witnessed for a == b is free
+        for tamper in [Tamper::EachCell, Tamper::Flip] {
+            let report = tamper_circuit(k, &circuit, vec![], tamper);
+            assert_eq!(report.accepted.len(), 1);
+            assert!(report.accepted[0].location.contains("'value inv'"));
+        }
+    }
+}
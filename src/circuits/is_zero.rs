@@ -1,7 +1,7 @@
 use halo2_proofs::{
-    circuit::{Layouter, SimpleFloorPlanner, Value},
+    circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
     halo2curves::FieldExt,
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector, VirtualCells},
     poly::Rotation,
 };
 
@@ -145,15 +145,102 @@ impl<F: FieldExt> Circuit<F> for IsZeroCircuit<F> {
     }
 }
 
+// The circuit above is a standalone demo. To use the same check inside a bigger circuit, we package it as a "chip":
+// the chip owns the columns and gate of the check, while the circuit embedding it decides which rows the check is enabled on and what value is checked.
+
+/// Columns of an [`IsZeroChip`], and the expression of its output.
+#[derive(Clone, Debug)]
+pub struct IsZeroChipConfig<F: FieldExt> {
+    pub value_inv: Column<Advice>,
+    pub out: Column<Advice>,
+    /// `out` at the current row, which is 1 if the value is zero and 0 otherwise
+    is_zero_expr: Expression<F>,
+}
+
+// The chip adds two columns to the circuit, and is enabled wherever `q_enable` is nonzero:
+// value_inv | out
+// with constraints, where `value` is any expression of the embedding circuit:
+// q_enable * (out - (1 - value * value_inv)) = 0
+// q_enable * value * out = 0
+// If value != 0, the second constraint forces out = 0, and then the first forces value_inv = value^{-1}.
+// If value == 0, the first constraint forces out = 1.
+
+/// Checks whether an expression is zero. The output is assigned to a cell, so it can be copied elsewhere, and is also available as an expression
+/// for use in other gates at the same row.
+#[derive(Clone, Debug)]
+pub struct IsZeroChip<F: FieldExt> {
+    config: IsZeroChipConfig<F>,
+}
+
+impl<F: FieldExt> IsZeroChip<F> {
+    pub fn construct(config: IsZeroChipConfig<F>) -> Self {
+        Self { config }
+    }
+
+    /// `q_enable` selects the rows the check applies to and `value` is the expression that is checked, both queried at the current row.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        value: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+    ) -> IsZeroChipConfig<F> {
+        let [value_inv, out] = [(); 2].map(|_| meta.advice_column());
+        meta.enable_equality(out);
+
+        // the expression of `out` is created inside the gate, so we save it for later use in other gates
+        let mut is_zero_expr = Expression::Constant(F::zero());
+        meta.create_gate("is_zero", |meta| {
+            let q_enable = q_enable(meta);
+            let value = value(meta);
+            let value_inv = meta.query_advice(value_inv, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            is_zero_expr = out.clone();
+
+            let one = Expression::Constant(F::one());
+            vec![
+                q_enable.clone() * (out.clone() - (one - value.clone() * value_inv)),
+                q_enable * value * out,
+            ]
+        });
+
+        IsZeroChipConfig { value_inv, out, is_zero_expr }
+    }
+
+    /// Assigns the witness of the check at row `offset` of `region`, where `value` is the value of the checked expression at that row.
+    /// Returns the output cell, which is 1 if `value` is zero and 0 otherwise.
+    pub fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        value: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        // if value is zero, any value_inv works, so we use 0
+        let value_inv = value.map(|value| value.invert().unwrap_or(F::zero()));
+        region.assign_advice(|| "value inv", self.config.value_inv, offset, || value_inv)?;
+        let out = value.map(|value| if value == F::zero() { F::one() } else { F::zero() });
+        region.assign_advice(|| "is_zero out", self.config.out, offset, || out)
+    }
+
+    /// The output at the current row, for use in other gates.
+    pub fn expr(&self) -> Expression<F> {
+        self.config.is_zero_expr.clone()
+    }
+}
+
 // cfg(test) tells rust to only compile this in test mode
 #[cfg(test)]
 mod test {
     use halo2_proofs::{
-        arithmetic::Field, circuit::Value, dev::MockProver, halo2curves::bn256::Fr,
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Selector},
+        poly::Rotation,
     };
     use rand::rngs::OsRng;
 
-    use super::IsZeroCircuit;
+    use super::{IsZeroChip, IsZeroChipConfig, IsZeroCircuit};
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
 
     // this marks the function as a test
     #[test]
@@ -173,4 +260,105 @@ mod test {
 
         MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
     }
+
+    #[test]
+    fn test_is_zero_circuit_tampered() {
+        let k = 5;
+        let circuit = IsZeroCircuit { x: Value::known(Fr::random(OsRng)) };
+        tamper_circuit(k, &circuit, vec![], Tamper::EachCell).assert_sound();
+
+        // when x = 0, any y satisfies x * y + out - 1 = 0, so y is free. This is fine since y is only a hint for x != 0
+        let circuit = IsZeroCircuit { x: Value::known(Fr::zero()) };
+        let report = tamper_circuit(k, &circuit, vec![], Tamper::EachCell);
+        assert_eq!(report.accepted.len(), 1);
+        assert!(report.accepted[0].location.contains("'y'"));
+    }
+
+    // a circuit that embeds the chip: each row checks whether `value` is zero, and a second gate uses `expr()` to compare the output with `expected`
+    #[derive(Clone, Debug)]
+    struct IsZeroChipTestConfig {
+        value: Column<Advice>,
+        expected: Column<Advice>,
+        q_enable: Selector,
+        is_zero: IsZeroChipConfig<Fr>,
+    }
+
+    struct IsZeroChipTestCircuit {
+        values: Vec<Fr>,
+    }
+
+    impl Circuit<Fr> for IsZeroChipTestCircuit {
+        type Config = IsZeroChipTestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let [value, expected] = [(); 2].map(|_| meta.advice_column());
+            let q_enable = meta.selector();
+            let is_zero = IsZeroChip::configure(
+                meta,
+                |meta| meta.query_selector(q_enable),
+                |meta| meta.query_advice(value, Rotation::cur()),
+            );
+            let is_zero_expr = IsZeroChip::construct(is_zero.clone()).expr();
+            meta.create_gate("expected", |meta| {
+                let q_enable = meta.query_selector(q_enable);
+                let expected = meta.query_advice(expected, Rotation::cur());
+                vec![q_enable * (expected - is_zero_expr)]
+            });
+            IsZeroChipTestConfig { value, expected, q_enable, is_zero }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = IsZeroChip::construct(config.is_zero.clone());
+            layouter.assign_region(
+                || "is_zero chip",
+                |mut region| {
+                    for (offset, value) in self.values.iter().enumerate() {
+                        config.q_enable.enable(&mut region, offset)?;
+                        let value = Value::known(*value);
+                        region.assign_advice(|| "value", config.value, offset, || value)?;
+                        let expected = value.map(|v| Fr::from(bool::from(v.is_zero()) as u64));
+                        region.assign_advice(
+                            || "expected",
+                            config.expected,
+                            offset,
+                            || expected,
+                        )?;
+                        chip.assign(&mut region, offset, value)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_is_zero_chip() {
+        let k = 5;
+        let values = vec![Fr::zero(), Fr::one(), Fr::random(OsRng), Fr::zero()];
+        let circuit = IsZeroChipTestCircuit { values };
+
+        MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_is_zero_chip_tampered() {
+        let k = 5;
+        let circuit =
+            IsZeroChipTestCircuit { values: vec![Fr::zero(), Fr::one(), Fr::random(OsRng)] };
+        // `Flip` negates the output of the chip, and only the inverse witnessed for 0 is free
+        for tamper in [Tamper::EachCell, Tamper::Flip] {
+            let report = tamper_circuit(k, &circuit, vec![], tamper);
+            assert_eq!(report.accepted.len(), 1);
+            assert!(report.accepted[0].location.contains("'value inv'"));
+        }
+    }
 }
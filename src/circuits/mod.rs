@@ -3,6 +3,10 @@
 //! This is intended for those who want to learn about how to use the raw Halo2 API and PLONKish arithmetization in general.
 //! If you are just getting started, we recommend looking at the `halo2-lib` examples mentioned in the README first.
 
+pub mod boolean;
+pub mod is_equal;
 pub mod is_zero;
 pub mod or;
+pub mod plonk_program;
+pub mod range_check;
 pub mod standard_plonk;
@@ -168,6 +168,7 @@ mod test {
     use halo2_proofs::{circuit::Value, dev::MockProver, halo2curves::bn256::Fr};
 
     use super::OrCircuit;
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
 
     // this marks the function as a test
     #[test]
@@ -178,4 +179,16 @@ mod test {
 
         MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
     }
+
+    #[test]
+    fn test_or_tampered() {
+        let k = 5;
+        let circuit = OrCircuit { a: Value::known(Fr::zero()), b: Value::known(Fr::zero()) };
+        tamper_circuit(k, &circuit, vec![], Tamper::EachCell).assert_sound();
+
+        // when b = 1 the OR gate holds for any a, and nothing constrains a to be a bit (and the same for b when a = 1)
+        let circuit = OrCircuit { a: Value::known(Fr::one()), b: Value::known(Fr::one()) };
+        let report = tamper_circuit(k, &circuit, vec![], Tamper::EachCell);
+        assert_eq!(report.accepted.len(), 2);
+    }
 }
@@ -0,0 +1,462 @@
+use std::{collections::HashMap, fmt};
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    halo2curves::FieldExt,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+use super::standard_plonk::StandardPlonkConfig;
+
+// `StandardPlonk` hardcodes its rows in `synthesize`. A `StandardPlonkProgram` instead reads the rows from a text file,
+// so that hand-written constraint systems can be tested without writing Rust for each of them.
+// Each row is one standard PLONK gate q_a·a + q_b·b + q_c·c + q_ab·a·b + constant = 0, and the cells a, b, c are named variables.
+// All cells with the same name are constrained to be equal, which is how gates are wired together.
+// For example, y = x^2 + 72 with y public is:
+//
+// # q_a q_b q_c q_ab constant : a b c
+// public y
+// gate 0 0 -1 1 0  : x x x2
+// gate 1 0 -1 0 72 : x2 _ y
+//
+// Lines starting with `#` are comments, and `_` is an unused cell, which is constrained to be 0.
+// `public` declares variables exposed in the instance column, in the order they are declared.
+// The witness is solved from the values of some input variables (here `x`) by going through the gates until every variable is known.
+
+/// A cell of a gate row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wire {
+    /// Index of the variable in [`StandardPlonkProgram::variables`]
+    Var(usize),
+    Unused,
+}
+
+/// One row of the standard PLONK gate `q_a·a + q_b·b + q_c·c + q_ab·a·b + constant = 0`.
+#[derive(Clone, Debug)]
+pub struct GateRow<F: FieldExt> {
+    pub q_a: F,
+    pub q_b: F,
+    pub q_c: F,
+    pub q_ab: F,
+    pub constant: F,
+    /// The cells `a, b, c`
+    pub wires: [Wire; 3],
+}
+
+impl<F: FieldExt> GateRow<F> {
+    fn is_satisfied(&self, [a, b, c]: [F; 3]) -> bool {
+        self.q_a * a + self.q_b * b + self.q_c * c + self.q_ab * a * b + self.constant == F::zero()
+    }
+
+    /// Solves the gate for the single unknown cell among `values`, if its coefficient is not zero.
+    fn solve(&self, values: [Option<F>; 3]) -> Option<(usize, F)> {
+        let (unknown, coeff, rest) = match values {
+            [None, Some(b), Some(c)] => {
+                (0, self.q_a + self.q_ab * b, self.q_b * b + self.q_c * c + self.constant)
+            }
+            [Some(a), None, Some(c)] => {
+                (1, self.q_b + self.q_ab * a, self.q_a * a + self.q_c * c + self.constant)
+            }
+            [Some(a), Some(b), None] => {
+                (2, self.q_c, self.q_a * a + self.q_b * b + self.q_ab * a * b + self.constant)
+            }
+            _ => return None,
+        };
+        let inv = Option::<F>::from(coeff.invert())?;
+        Some((unknown, -rest * inv))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProgramError {
+    /// Line `line` (starting from 1) of the program text is invalid
+    Parse {
+        line: usize,
+        reason: String,
+    },
+    UnknownVariable(String),
+    /// These variables could not be solved from the inputs
+    Unsolved(Vec<String>),
+    /// The gate at this row is not satisfied by the solved witness, because the inputs contradict each other
+    Unsatisfied {
+        row: usize,
+    },
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { line, reason } => write!(f, "line {line}: {reason}"),
+            Self::UnknownVariable(name) => write!(f, "unknown variable `{name}`"),
+            Self::Unsolved(names) => {
+                write!(f, "could not solve the variables {} from the inputs", names.join(", "))
+            }
+            Self::Unsatisfied { row } => {
+                write!(f, "the inputs do not satisfy the gate at row {row}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+/// A circuit made of standard PLONK gate rows, see the top of this file for the text format.
+#[derive(Clone, Debug)]
+pub struct StandardPlonkProgram<F: FieldExt> {
+    pub rows: Vec<GateRow<F>>,
+    /// Names of the variables, in order of first appearance
+    pub variables: Vec<String>,
+    /// Variables exposed in the instance column, in order
+    pub public: Vec<usize>,
+}
+
+impl<F: FieldExt> StandardPlonkProgram<F> {
+    pub fn parse(text: &str) -> Result<Self, ProgramError> {
+        let mut program = Self { rows: vec![], variables: vec![], public: vec![] };
+        let mut indices = HashMap::new();
+        // line of the declaration of each public variable, for errors
+        let mut public_lines = vec![];
+        let mut variable = |name: &str, variables: &mut Vec<String>| {
+            *indices.entry(name.to_string()).or_insert_with(|| {
+                variables.push(name.to_string());
+                variables.len() - 1
+            })
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let err = |reason: String| ProgramError::Parse { line: i + 1, reason };
+            let line = line.split('#').next().unwrap().trim();
+            let Some((keyword, rest)) = line.split_once(char::is_whitespace) else {
+                if line.is_empty() {
+                    continue;
+                }
+                return Err(err(format!("expected `public` or `gate`, found `{line}`")));
+            };
+            match keyword {
+                "public" => {
+                    for name in rest.split_whitespace() {
+                        let var = variable(name, &mut program.variables);
+                        program.public.push(var);
+                        public_lines.push(i + 1);
+                    }
+                }
+                "gate" => {
+                    let (coeffs, wires) = rest.split_once(':').ok_or_else(|| {
+                        err("expected `:` between the selectors and the cells".into())
+                    })?;
+                    let coeffs = coeffs
+                        .split_whitespace()
+                        .map(|c| {
+                            parse_field(c)
+                                .ok_or_else(|| err(format!("invalid field element `{c}`")))
+                        })
+                        .collect::<Result<Vec<F>, _>>()?;
+                    let [q_a, q_b, q_c, q_ab, constant]: [F; 5] =
+                        coeffs.try_into().map_err(|coeffs: Vec<F>| {
+                            err(format!(
+                                "expected 5 selectors q_a q_b q_c q_ab constant, found {}",
+                                coeffs.len()
+                            ))
+                        })?;
+                    let wires = wires
+                        .split_whitespace()
+                        .map(|name| match name {
+                            "_" => Wire::Unused,
+                            name => Wire::Var(variable(name, &mut program.variables)),
+                        })
+                        .collect::<Vec<_>>();
+                    let wires = wires.try_into().map_err(|wires: Vec<Wire>| {
+                        err(format!("expected 3 cells a b c, found {}", wires.len()))
+                    })?;
+                    program.rows.push(GateRow { q_a, q_b, q_c, q_ab, constant, wires });
+                }
+                _ => return Err(err(format!("expected `public` or `gate`, found `{keyword}`"))),
+            }
+        }
+
+        // a public variable that is in no gate has no cell to constrain to the instance column
+        for (&var, &line) in program.public.iter().zip(&public_lines) {
+            if !program.rows.iter().any(|row| row.wires.contains(&Wire::Var(var))) {
+                let reason =
+                    format!("public variable `{}` is not used in any gate", program.variables[var]);
+                return Err(ProgramError::Parse { line, reason });
+            }
+        }
+        Ok(program)
+    }
+
+    /// Solves the values of all variables, in the order of [`Self::variables`], from the values of `inputs`.
+    pub fn solve(&self, inputs: &[(&str, F)]) -> Result<Vec<F>, ProgramError> {
+        let mut values = vec![None; self.variables.len()];
+        for (name, value) in inputs {
+            let var = self
+                .variables
+                .iter()
+                .position(|v| v == name)
+                .ok_or_else(|| ProgramError::UnknownVariable(name.to_string()))?;
+            values[var] = Some(*value);
+        }
+
+        let cell = |wire: Wire, values: &[Option<F>]| match wire {
+            Wire::Var(var) => values[var],
+            Wire::Unused => Some(F::zero()),
+        };
+        // gates may be in any order, so we go through them until no new variable is solved
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for row in &self.rows {
+                if let Some((i, value)) = row.solve(row.wires.map(|wire| cell(wire, &values))) {
+                    if let Wire::Var(var) = row.wires[i] {
+                        values[var] = Some(value);
+                        progress = true;
+                    }
+                }
+            }
+        }
+
+        let unsolved = values
+            .iter()
+            .zip(&self.variables)
+            .filter(|(value, _)| value.is_none())
+            .map(|(_, name)| name.clone())
+            .collect::<Vec<_>>();
+        if !unsolved.is_empty() {
+            return Err(ProgramError::Unsolved(unsolved));
+        }
+        for (row, gate) in self.rows.iter().enumerate() {
+            if !gate.is_satisfied(gate.wires.map(|wire| cell(wire, &values).unwrap())) {
+                return Err(ProgramError::Unsatisfied { row });
+            }
+        }
+        Ok(values.into_iter().map(Option::unwrap).collect())
+    }
+
+    /// The values of the instance column, given the values of all variables.
+    pub fn instances(&self, witness: &[F]) -> Vec<F> {
+        self.public.iter().map(|var| witness[*var]).collect()
+    }
+
+    /// Smallest `k` such that the circuit fits in `2^k` rows, leaving room for the blinding factors.
+    pub fn min_k(&self) -> u32 {
+        let rows = self.rows.len() + 10;
+        rows.next_power_of_two().trailing_zeros().max(4)
+    }
+}
+
+/// Parses a decimal field element, possibly negative.
+fn parse_field<F: FieldExt>(s: &str) -> Option<F> {
+    match s.strip_prefix('-') {
+        Some(abs) => F::from_str_vartime(abs).map(|x| -x),
+        None => F::from_str_vartime(s),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct StandardPlonkProgramConfig<F: FieldExt> {
+    pub plonk: StandardPlonkConfig<F>,
+    pub instance: Column<Instance>,
+}
+
+/// The circuit of a [`StandardPlonkProgram`] with the values of its variables.
+#[derive(Clone)]
+pub struct StandardPlonkProgramCircuit<F: FieldExt> {
+    pub program: StandardPlonkProgram<F>,
+    /// The values of all variables, e.g. from [`StandardPlonkProgram::solve`]
+    pub witness: Value<Vec<F>>,
+}
+
+impl<F: FieldExt> Circuit<F> for StandardPlonkProgramCircuit<F> {
+    type Config = StandardPlonkProgramConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self { program: self.program.clone(), witness: Value::unknown() }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let plonk = StandardPlonkConfig::configure(meta);
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        // holds the 0 that unused cells are copied from
+        let zero = meta.fixed_column();
+        meta.enable_constant(zero);
+        StandardPlonkProgramConfig { plonk, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let StandardPlonkConfig { a, b, c, q_a, q_b, q_c, q_ab, constant, .. } = config.plonk;
+        let public_cells = layouter.assign_region(
+            || "program",
+            |mut region| {
+                // the first cell of each variable, which all its other cells are constrained to equal
+                let mut cells: Vec<Option<AssignedCell<F, F>>> =
+                    vec![None; self.program.variables.len()];
+                // the first unused cell, the only one copied from the fixed 0: each constant takes its own row of the fixed column,
+                // so copying every unused cell from a constant could need more rows than the program has
+                let mut zero: Option<AssignedCell<F, F>> = None;
+                for (offset, row) in self.program.rows.iter().enumerate() {
+                    let selectors = [
+                        (q_a, row.q_a),
+                        (q_b, row.q_b),
+                        (q_c, row.q_c),
+                        (q_ab, row.q_ab),
+                        (constant, row.constant),
+                    ];
+                    for (column, value) in selectors {
+                        region.assign_fixed(
+                            || "selector",
+                            column,
+                            offset,
+                            || Value::known(value),
+                        )?;
+                    }
+                    for (column, wire) in [a, b, c].into_iter().zip(row.wires) {
+                        let Wire::Var(var) = wire else {
+                            // without the copy constraint, the prover could put any value in an unused cell with a nonzero selector
+                            match &zero {
+                                Some(zero) => {
+                                    zero.copy_advice(|| "unused", &mut region, column, offset)?;
+                                }
+                                None => {
+                                    zero = Some(region.assign_advice_from_constant(
+                                        || "unused",
+                                        column,
+                                        offset,
+                                        F::zero(),
+                                    )?);
+                                }
+                            }
+                            continue;
+                        };
+                        let value = self.witness.as_ref().map(|witness| witness[var]);
+                        let cell = region.assign_advice(|| "cell", column, offset, || value)?;
+                        match &cells[var] {
+                            Some(first) => region.constrain_equal(first.cell(), cell.cell())?,
+                            None => cells[var] = Some(cell),
+                        }
+                    }
+                }
+                // `parse` checks that every public variable is in some gate
+                Ok(self
+                    .program
+                    .public
+                    .iter()
+                    .map(|var| cells[*var].clone().unwrap())
+                    .collect::<Vec<_>>())
+            },
+        )?;
+        for (row, cell) in public_cells.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), config.instance, row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_proofs::{circuit::Value, dev::MockProver, halo2curves::bn256::Fr};
+
+    use super::{ProgramError, StandardPlonkProgram, StandardPlonkProgramCircuit};
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
+
+    // y = x^2 + 72 and z = x * y, with y and z public
+    const PROGRAM: &str = "
+        # q_a q_b q_c q_ab constant : a b c
+        public y z
+        gate 0 0 -1 1 0  : x x x2
+        gate 1 0 -1 0 72 : x2 _ y
+        gate 0 0 -1 1 0  : x y z
+    ";
+
+    #[test]
+    fn test_plonk_program() {
+        let program = StandardPlonkProgram::<Fr>::parse(PROGRAM).unwrap();
+        let witness = program.solve(&[("x", Fr::from(3))]).unwrap();
+        let instances = program.instances(&witness);
+        assert_eq!(instances, vec![Fr::from(81), Fr::from(243)]);
+
+        let k = program.min_k();
+        let circuit = StandardPlonkProgramCircuit { program, witness: Value::known(witness) };
+        MockProver::run(k, &circuit, vec![instances]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_plonk_program_solve_backwards() {
+        // x is solved from z = x * y through the last gate, and only then x2 from the first gate
+        let program = StandardPlonkProgram::<Fr>::parse(PROGRAM).unwrap();
+        let witness = program.solve(&[("y", Fr::from(81)), ("z", Fr::from(243))]).unwrap();
+        let x = program.variables.iter().position(|v| v == "x").unwrap();
+        assert_eq!(witness[x], Fr::from(3));
+
+        let err = program.solve(&[("x2", Fr::from(10)), ("x", Fr::from(3))]).unwrap_err();
+        assert_eq!(err, ProgramError::Unsatisfied { row: 0 });
+        let err = program.solve(&[]).unwrap_err();
+        assert!(matches!(err, ProgramError::Unsolved(_)));
+    }
+
+    #[test]
+    fn test_plonk_program_wrong_instance() {
+        let program = StandardPlonkProgram::<Fr>::parse(PROGRAM).unwrap();
+        let witness = program.solve(&[("x", Fr::from(3))]).unwrap();
+        let k = program.min_k();
+        let circuit = StandardPlonkProgramCircuit { program, witness: Value::known(witness) };
+
+        let instances = vec![Fr::from(81), Fr::from(244)];
+        assert!(MockProver::run(k, &circuit, vec![instances]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_plonk_program_wrong_witness() {
+        // changing x after solving breaks the gates that use it
+        let program = StandardPlonkProgram::<Fr>::parse(PROGRAM).unwrap();
+        let mut witness = program.solve(&[("x", Fr::from(3))]).unwrap();
+        let instances = program.instances(&witness);
+        witness[0] = Fr::from(4);
+        let k = program.min_k();
+        let circuit = StandardPlonkProgramCircuit { program, witness: Value::known(witness) };
+
+        assert!(MockProver::run(k, &circuit, vec![instances]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_plonk_program_unused_cells() {
+        // the unused cell of the second gate has q_b = 0, so only its copy constraint to 0 stops it from changing.
+        // With a nonzero selector, a free unused cell would let the prover choose the value of another cell of the gate.
+        let program = StandardPlonkProgram::<Fr>::parse(PROGRAM).unwrap();
+        let witness = program.solve(&[("x", Fr::from(3))]).unwrap();
+        let instances = program.instances(&witness);
+        let k = program.min_k();
+        let circuit = StandardPlonkProgramCircuit { program, witness: Value::known(witness) };
+        tamper_circuit(k, &circuit, vec![instances], Tamper::EachCell).assert_sound();
+    }
+
+    #[test]
+    fn test_plonk_program_many_unused_cells() {
+        // constant assertions leave two cells unused per row, so there are more unused cells than rows
+        let mut text = "public x\n".to_string();
+        for _ in 0..60 {
+            text.push_str("gate 1 0 0 0 -7 : x _ _\n");
+        }
+        let program = StandardPlonkProgram::<Fr>::parse(&text).unwrap();
+        let witness = program.solve(&[("x", Fr::from(7))]).unwrap();
+        let instances = program.instances(&witness);
+        let k = program.min_k();
+        let circuit = StandardPlonkProgramCircuit { program, witness: Value::known(witness) };
+        MockProver::run(k, &circuit, vec![instances]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_plonk_program_parse_errors() {
+        let parse = |text| StandardPlonkProgram::<Fr>::parse(text).unwrap_err();
+        assert!(matches!(parse("gate 1 2 3 : a b c"), ProgramError::Parse { line: 1, .. }));
+        assert!(matches!(parse("gate 0 0 0 0 0 : a b"), ProgramError::Parse { line: 1, .. }));
+        assert!(matches!(parse("\ngate 0 0 0 0 x : a b c"), ProgramError::Parse { line: 2, .. }));
+        assert!(matches!(parse("wire a b"), ProgramError::Parse { line: 1, .. }));
+        assert!(matches!(parse("\n\npublic a"), ProgramError::Parse { line: 3, .. }));
+    }
+}
@@ -0,0 +1,441 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, Value},
+    halo2curves::FieldExt,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector, TableColumn},
+    poly::Rotation,
+};
+
+// A range check with a lookup table, the raw halo2 counterpart of halo2-lib's `RangeChip`.
+// The table is a fixed `TableColumn` containing 0, 1, ..., 2^lookup_bits - 1.
+// To check that `value` has at most N = lookup_bits * num_limbs bits, we decompose it into limbs of `lookup_bits` bits with a running sum:
+// z_0 = value, z_{i+1} = (z_i - limb_i) / 2^lookup_bits, so that limb_i = z_i - 2^lookup_bits * z_{i+1}.
+// | z           | q_step | q_last |
+// | z_0         | 1      |        |
+// | z_1         | 1      |        |
+// | ...         |        |        |
+// | z_{n-1}     |        | 1      |
+// with a single lookup of `q_step * (z_cur - 2^lookup_bits * z_next) + q_last * z_cur` into the table.
+// Looking up z_{n-1} itself forces the running sum to end at z_n = 0, i.e. value = sum_i limb_i * 2^(i * lookup_bits) < 2^N.
+// Rows without a selector look up 0, which is in the table.
+// Lookups can only use selectors created with `complex_selector`.
+
+#[derive(Clone, Copy, Debug)]
+pub struct RangeCheckConfig {
+    pub z: Column<Advice>,
+    pub table: TableColumn,
+    q_step: Selector,
+    q_last: Selector,
+    pub lookup_bits: usize,
+    pub num_limbs: usize,
+}
+
+impl RangeCheckConfig {
+    /// Configures range checks of values with at most `lookup_bits * num_limbs` bits, which must be less than 128.
+    pub fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        lookup_bits: usize,
+        num_limbs: usize,
+    ) -> Self {
+        assert!(num_limbs > 0, "a range check needs at least one limb");
+        assert!(lookup_bits * num_limbs < 128, "range checks are limited to less than 128 bits");
+        let z = meta.advice_column();
+        meta.enable_equality(z);
+        let table = meta.lookup_table_column();
+        let [q_step, q_last] = [(); 2].map(|_| meta.complex_selector());
+
+        meta.lookup("range check limb", |meta| {
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+            let q_step = meta.query_selector(q_step);
+            let q_last = meta.query_selector(q_last);
+            let radix = Expression::Constant(F::from_u128(1 << lookup_bits));
+            let limb = q_step * (z_cur.clone() - radix * z_next) + q_last * z_cur;
+            vec![(limb, table)]
+        });
+
+        Self { z, table, q_step, q_last, lookup_bits, num_limbs }
+    }
+
+    /// Number of bits of the values that are checked.
+    pub fn num_bits(&self) -> usize {
+        self.lookup_bits * self.num_limbs
+    }
+}
+
+/// Checks that values fit in `lookup_bits * num_limbs` bits.
+#[derive(Clone, Debug)]
+pub struct RangeCheckChip<F: FieldExt> {
+    config: RangeCheckConfig,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<F: FieldExt> RangeCheckChip<F> {
+    pub fn construct(config: RangeCheckConfig) -> Self {
+        Self { config, _marker: std::marker::PhantomData }
+    }
+
+    /// Fills the lookup table. This must be called exactly once per circuit.
+    pub fn load_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range table",
+            |mut table| {
+                for i in 0..1usize << self.config.lookup_bits {
+                    table.assign_cell(
+                        || "range table value",
+                        self.config.table,
+                        i,
+                        || Value::known(F::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assigns the running sum of `value` to the `num_limbs` rows of `region` starting at `offset`, and returns the cell of `value`.
+    /// Copy a cell into the returned one to range check it.
+    pub fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        value: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let RangeCheckConfig { z, lookup_bits, num_limbs, .. } = self.config;
+        let radix_inv = F::from_u128(1 << lookup_bits).invert().unwrap();
+        let mask = (1u128 << lookup_bits) - 1;
+
+        let first = region.assign_advice(|| "z_0", z, offset, || value)?;
+        let mut z_i = value;
+        for i in 0..num_limbs {
+            if i + 1 < num_limbs {
+                self.config.q_step.enable(region, offset + i)?;
+                // the limb is only correct if z_i is in range; otherwise the lookup fails anyway
+                z_i = z_i.map(|z_i| (z_i - F::from_u128(z_i.get_lower_128() & mask)) * radix_inv);
+                region.assign_advice(|| "z", z, offset + i + 1, || z_i)?;
+            } else {
+                self.config.q_last.enable(region, offset + i)?;
+            }
+        }
+        Ok(first)
+    }
+
+    /// Range checks `cell` by copying it into the running sum assigned at `offset`, using `num_limbs` rows of `region`.
+    pub fn range_check(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        cell: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        let z_0 = self.assign(region, offset, cell.value().copied())?;
+        region.constrain_equal(cell.cell(), z_0.cell())
+    }
+}
+
+// To compare two values a, b with at most N bits each, we witness lt = (a < b) and range check
+// diff = a - b + lt * 2^N.
+// If lt = 1 then a < b exactly when diff = 2^N - (b - a) is in [0, 2^N), and if lt = 0 then a >= b exactly when diff = a - b is in [0, 2^N).
+// | a | b | lt | z (of the range check) | q_lt |
+// | a | b | lt | diff                   | 1    |
+// with constraints:
+// LT:   q_lt * (a - b + lt * 2^N - diff) = 0
+// BOOL: q_lt * lt * (lt - 1) = 0
+// The inputs must themselves have at most N bits: otherwise, e.g. a = p - 1 and b = 0 give diff = 2^N - 1 with lt = 1.
+// `LtChip::assign` range checks them with the same table, in the rows of z after diff, while `LtChip::assign_unchecked` is for inputs that are already range checked.
+// a and b are copied from cells of the caller, so that the output is a statement about those cells.
+
+#[derive(Clone, Copy, Debug)]
+pub struct LtConfig {
+    pub a: Column<Advice>,
+    pub b: Column<Advice>,
+    pub lt: Column<Advice>,
+    q_lt: Selector,
+    pub range: RangeCheckConfig,
+}
+
+impl LtConfig {
+    /// Configures comparisons of values with at most `range.num_bits()` bits, using the range check `range` on the difference.
+    pub fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>, range: RangeCheckConfig) -> Self {
+        let [a, b, lt] = [(); 3].map(|_| meta.advice_column());
+        [a, b, lt].map(|column| meta.enable_equality(column));
+        let q_lt = meta.selector();
+
+        meta.create_gate("LT gate", |meta| {
+            let [a, b, lt] = [a, b, lt].map(|column| meta.query_advice(column, Rotation::cur()));
+            let diff = meta.query_advice(range.z, Rotation::cur());
+            let q_lt = meta.query_selector(q_lt);
+            let two_pow_n = Expression::Constant(F::from_u128(1 << range.num_bits()));
+            let one = Expression::Constant(F::one());
+            vec![
+                q_lt.clone() * (a - b + lt.clone() * two_pow_n - diff),
+                q_lt * lt.clone() * (lt - one),
+            ]
+        });
+
+        Self { a, b, lt, q_lt, range }
+    }
+}
+
+/// Computes `a < b` for values with at most `lookup_bits * num_limbs` bits.
+#[derive(Clone, Debug)]
+pub struct LtChip<F: FieldExt> {
+    config: LtConfig,
+    range: RangeCheckChip<F>,
+}
+
+impl<F: FieldExt> LtChip<F> {
+    pub fn construct(config: LtConfig) -> Self {
+        Self { config, range: RangeCheckChip::construct(config.range) }
+    }
+
+    /// Fills the lookup table of the range check. This must be called exactly once per circuit.
+    pub fn load_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.range.load_table(layouter)
+    }
+
+    /// Copies `a` and `b` to row `offset` of `region` and assigns their comparison, range checking both inputs.
+    /// Uses `3 * num_limbs` rows of the range check column from `offset` on.
+    /// Returns the output cell, which is 1 if `a < b` and 0 otherwise.
+    pub fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let num_limbs = self.config.range.num_limbs;
+        self.range.range_check(region, offset + num_limbs, a)?;
+        self.range.range_check(region, offset + 2 * num_limbs, b)?;
+        self.assign_unchecked(region, offset, a, b)
+    }
+
+    /// Same as [`Self::assign`], but only uses `num_limbs` rows of the range check column, because `a` and `b` must already have at most N bits.
+    pub fn assign_unchecked(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let lt = a
+            .value()
+            .zip(b.value())
+            .map(|(a, b)| F::from((a.get_lower_128() < b.get_lower_128()) as u64));
+        self.assign_with_lt(region, offset, a, b, lt)
+    }
+
+    fn assign_with_lt(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+        lt: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.config.q_lt.enable(region, offset)?;
+        let a = a.copy_advice(|| "a", region, self.config.a, offset)?;
+        let b = b.copy_advice(|| "b", region, self.config.b, offset)?;
+        let out = region.assign_advice(|| "lt", self.config.lt, offset, || lt)?;
+        let two_pow_n = F::from_u128(1 << self.config.range.num_bits());
+        let diff = a.value().zip(b.value()).zip(lt).map(|((a, b), lt)| *a - *b + lt * two_pow_n);
+        self.range.assign(region, offset, diff)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+    };
+
+    use super::{LtChip, LtConfig, RangeCheckChip, RangeCheckConfig};
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
+
+    const LOOKUP_BITS: usize = 4;
+    const NUM_LIMBS: usize = 2;
+
+    struct RangeCheckTestCircuit {
+        values: Vec<Fr>,
+    }
+
+    impl Circuit<Fr> for RangeCheckTestCircuit {
+        type Config = RangeCheckConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            RangeCheckConfig::configure(meta, LOOKUP_BITS, NUM_LIMBS)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = RangeCheckChip::construct(config);
+            chip.load_table(&mut layouter)?;
+            layouter.assign_region(
+                || "range checks",
+                |mut region| {
+                    for (i, value) in self.values.iter().enumerate() {
+                        chip.assign(&mut region, i * NUM_LIMBS, Value::known(*value))?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_range_check() {
+        let k = 6;
+        let values = [0u64, 1, 15, 16, 200, 255].map(Fr::from).to_vec();
+        let circuit = RangeCheckTestCircuit { values };
+
+        MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_range_check_out_of_range() {
+        let k = 6;
+        for value in [Fr::from(256), Fr::from(1 << 20), -Fr::one()] {
+            let circuit = RangeCheckTestCircuit { values: vec![value] };
+
+            assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum LtWitness {
+        /// Assigned with `LtChip::assign`, which range checks the inputs
+        Checked,
+        /// Assigned with `LtChip::assign_unchecked`
+        Unchecked,
+        /// The opposite of `a < b` with a consistent difference, as a malicious prover would assign it, so that only the range check of the difference can catch it.
+        /// Tampering with a single cell, as `tamper_circuit` does, cannot produce this witness
+        Forged,
+    }
+
+    struct LtTestCircuit {
+        pairs: Vec<(Fr, Fr)>,
+        witness: LtWitness,
+    }
+
+    impl Circuit<Fr> for LtTestCircuit {
+        // the inputs are assigned to their own column and copied by the chip
+        type Config = (LtConfig, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let range = RangeCheckConfig::configure(meta, LOOKUP_BITS, NUM_LIMBS);
+            let input = meta.advice_column();
+            meta.enable_equality(input);
+            (LtConfig::configure(meta, range), input)
+        }
+
+        fn synthesize(
+            &self,
+            (config, input): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = LtChip::construct(config);
+            chip.load_table(&mut layouter)?;
+            layouter.assign_region(
+                || "lt chip",
+                |mut region| {
+                    for (i, (a, b)) in self.pairs.iter().enumerate() {
+                        let offset = i * 3 * NUM_LIMBS;
+                        let [a, b] = [(a, 0), (b, 1)].map(|(x, row)| {
+                            region.assign_advice(
+                                || "input",
+                                input,
+                                offset + row,
+                                || Value::known(*x),
+                            )
+                        });
+                        let (a, b) = (a?, b?);
+                        let out = match self.witness {
+                            LtWitness::Checked => chip.assign(&mut region, offset, &a, &b)?,
+                            LtWitness::Unchecked => {
+                                chip.assign_unchecked(&mut region, offset, &a, &b)?
+                            }
+                            LtWitness::Forged => {
+                                let lt = a.value().zip(b.value()).map(|(a, b)| {
+                                    Fr::from((a.get_lower_128() >= b.get_lower_128()) as u64)
+                                });
+                                chip.assign_with_lt(&mut region, offset, &a, &b, lt)?
+                            }
+                        };
+                        let expected = a
+                            .value()
+                            .zip(b.value())
+                            .map(|(a, b)| a.get_lower_128() < b.get_lower_128());
+                        out.value().zip(expected).assert_if_known(|(out, expected)| {
+                            (**out == Fr::from(*expected as u64))
+                                != (self.witness == LtWitness::Forged)
+                        });
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_lt() {
+        let k = 7;
+        let pairs =
+            [(0u64, 0u64), (0, 1), (1, 0), (3, 200), (200, 3), (255, 255), (254, 255), (255, 0)]
+                .map(|(a, b)| (Fr::from(a), Fr::from(b)))
+                .to_vec();
+        for witness in [LtWitness::Checked, LtWitness::Unchecked] {
+            let circuit = LtTestCircuit { pairs: pairs.clone(), witness };
+
+            MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_lt_tampered() {
+        let k = 6;
+        let pairs = [(0u64, 0u64), (3, 200), (200, 3), (255, 0)]
+            .map(|(a, b)| (Fr::from(a), Fr::from(b)))
+            .to_vec();
+        let circuit = LtTestCircuit { pairs: pairs.clone(), witness: LtWitness::Checked };
+        // `Flip` negates the output of the chip
+        for tamper in [Tamper::EachCell, Tamper::Flip] {
+            tamper_circuit(k, &circuit, vec![], tamper).assert_sound();
+        }
+
+        for pair in pairs {
+            let circuit = LtTestCircuit { pairs: vec![pair], witness: LtWitness::Forged };
+
+            assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_lt_out_of_range() {
+        let k = 6;
+        // without checking the inputs, p - 1 < 0 can be claimed with diff = p - 1 + 2^N = 2^N - 1, which passes the range check of the difference
+        let pairs = vec![(-Fr::one(), Fr::zero())];
+        let unchecked = LtTestCircuit { pairs: pairs.clone(), witness: LtWitness::Forged };
+        assert!(MockProver::run(k, &unchecked, vec![]).unwrap().verify().is_ok());
+
+        for pairs in [pairs, vec![(Fr::zero(), -Fr::one())], vec![(Fr::from(256), Fr::from(3))]] {
+            let circuit = LtTestCircuit { pairs, witness: LtWitness::Checked };
+
+            assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+        }
+    }
+}
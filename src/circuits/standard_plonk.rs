@@ -10,16 +10,14 @@ use halo2_proofs::{
 #[derive(Clone, Copy)]
 // it is standard practice to define everything where numbers are in a generic prime field `F` (`FieldExt` are the traits of a prime field)
 pub struct StandardPlonkConfig<F: FieldExt> {
-    a: Column<Advice>,
-    b: Column<Advice>,
-    c: Column<Advice>,
-    #[allow(dead_code)]
-    q_a: Column<Fixed>,
-    #[allow(dead_code)]
-    q_b: Column<Fixed>,
-    q_c: Column<Fixed>,
-    q_ab: Column<Fixed>,
-    constant: Column<Fixed>,
+    pub a: Column<Advice>,
+    pub b: Column<Advice>,
+    pub c: Column<Advice>,
+    pub q_a: Column<Fixed>,
+    pub q_b: Column<Fixed>,
+    pub q_c: Column<Fixed>,
+    pub q_ab: Column<Fixed>,
+    pub constant: Column<Fixed>,
     _marker: PhantomData<F>,
 }
 
@@ -121,6 +119,7 @@ mod test {
     use rand::rngs::OsRng;
 
     use super::StandardPlonk;
+    use crate::scaffold::testing::{tamper_circuit, Tamper};
 
     #[test]
     fn test_standard_plonk() {
@@ -129,4 +128,13 @@ mod test {
 
         MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
     }
+
+    #[test]
+    fn test_standard_plonk_tampered() {
+        let k = 5;
+        let circuit = StandardPlonk { x: Value::known(Fr::random(OsRng)) };
+
+        tamper_circuit(k, &circuit, vec![], Tamper::EachCell).assert_sound();
+        tamper_circuit(k, &circuit, vec![], Tamper::Random { samples: 10, seed: 0 }).assert_sound();
+    }
 }
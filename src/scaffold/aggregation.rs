@@ -0,0 +1,125 @@
+//! Scaffolding for the aggregation circuit, which verifies a list of snarks created by [`super::prove`] inside a single circuit.
+//!
+//! The public instances of the aggregation circuit are the KZG accumulator (`4 * LIMBS` field elements), followed by the concatenated public instances of all aggregated snarks.
+//! A verifier of the aggregation snark must additionally check the accumulator with a pairing, which [`super::verify`] does.
+use std::{env::set_var, path::PathBuf};
+
+use axiom_eth::util::{
+    circuit::{PinnableCircuit, PreCircuit},
+    AggregationConfigPinning,
+};
+use halo2_base::{
+    gates::builder::CircuitBuilderStage,
+    halo2_proofs::{
+        halo2curves::{
+            bn256::{Bn256, Fq, Fr, G1Affine},
+            pairing::Engine,
+        },
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    },
+    utils::{biguint_to_fe, fe_to_biguint},
+};
+use snark_verifier_sdk::{
+    halo2::{aggregation::AggregationCircuit, read_snark},
+    Snark, BITS, LIMBS, SHPLONK,
+};
+
+use super::{
+    cmd::Cli, proof::ProofOptions, CircuitKind, CircuitPaths, ScaffoldError, ScaffoldPreCircuit,
+};
+
+pub struct AggregationScaffold {
+    pub snarks: Vec<Snark>,
+}
+
+impl AggregationScaffold {
+    /// Reads the snarks to aggregate from the data directory. If no snarks are specified, aggregates `{name}.snark`.
+    pub fn from_cli(cli: &Cli, snarks: &[PathBuf]) -> Result<Self, ScaffoldError> {
+        let data_path = CircuitPaths::from_cli(cli).data_path;
+        let snark_paths = if snarks.is_empty() {
+            vec![data_path.join(format!("{}.snark", cli.name))]
+        } else {
+            snarks.iter().map(|snark| data_path.join(snark)).collect()
+        };
+        let snarks = snark_paths
+            .into_iter()
+            .map(|path| {
+                // the aggregation circuit only verifies proofs with a Poseidon transcript and SHPLONK
+                let options = ProofOptions::read_for(&path)?;
+                if options != ProofOptions::default() {
+                    return Err(ScaffoldError::IncompatibleSnark { path, options });
+                }
+                read_snark(&path).map_err(|source| ScaffoldError::MissingSnark { path, source })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { snarks })
+    }
+
+    pub fn paths(cli: &Cli) -> CircuitPaths {
+        CircuitPaths::from_cli(cli).aggregation()
+    }
+}
+
+impl PreCircuit for AggregationScaffold {
+    type Pinning = AggregationConfigPinning;
+
+    fn create_circuit(
+        self,
+        stage: CircuitBuilderStage,
+        pinning: Option<Self::Pinning>,
+        params: &ParamsKZG<Bn256>,
+    ) -> impl PinnableCircuit<Fr> {
+        // the aggregation circuit does a lot of non-native field arithmetic, so we use the largest lookup table possible
+        let lookup_bits = match &pinning {
+            Some(pinning) => pinning.params.lookup_bits,
+            None => params.k() as usize - 1,
+        };
+        let break_points = pinning.map(|p| p.break_points);
+        // `public` re-exposes the public instances of the aggregated snarks after the accumulator
+        let circuit = AggregationCircuit::public::<SHPLONK>(
+            stage,
+            break_points,
+            lookup_bits,
+            params,
+            self.snarks,
+            false,
+        );
+        if stage != CircuitBuilderStage::Prover {
+            // `AggregationCircuit::configure` is halo2-lib's `RangeCircuitBuilder::configure`, which only reads its parameters from process environment variables.
+            // `config` sets the number of columns there, and the lookup bits are set next to it. With a pinning, `AggregationConfigPinning::set_var` sets both.
+            // Aggregation circuits are only created on the main thread: the `aggregate` command has no batch mode.
+            set_var("LOOKUP_BITS", lookup_bits.to_string());
+            circuit.config(params.k(), Some(10));
+        }
+        circuit
+    }
+}
+
+impl ScaffoldPreCircuit for AggregationScaffold {
+    const KIND: CircuitKind = CircuitKind::Aggregation;
+}
+
+/// The aggregation circuit only defers the final pairing check of the aggregated snarks: the accumulator `(lhs, rhs)` in the first `4 * LIMBS` public instances
+/// is valid if and only if `e(lhs, g2) = e(rhs, s * g2)`.
+pub fn check_accumulator(params: &ParamsKZG<Bn256>, instances: &[Fr]) -> Result<(), ScaffoldError> {
+    if instances.len() < 4 * LIMBS {
+        return Err(ScaffoldError::InvalidAccumulator);
+    }
+    // each coordinate is split into `LIMBS` limbs of `BITS` bits, least significant limb first
+    let [lhs_x, lhs_y, rhs_x, rhs_y] = [0, 1, 2, 3].map(|i| {
+        let limbs = &instances[i * LIMBS..(i + 1) * LIMBS];
+        let coordinate =
+            limbs.iter().rev().map(fe_to_biguint).reduce(|acc, limb| (acc << BITS) + limb).unwrap();
+        biguint_to_fe::<Fq>(&coordinate)
+    });
+    let lhs = Option::<G1Affine>::from(G1Affine::from_xy(lhs_x, lhs_y));
+    let rhs = Option::<G1Affine>::from(G1Affine::from_xy(rhs_x, rhs_y));
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs))
+            if Bn256::pairing(&lhs, &params.g2()) == Bn256::pairing(&rhs, &params.s_g2()) =>
+        {
+            Ok(())
+        }
+        _ => Err(ScaffoldError::InvalidAccumulator),
+    }
+}
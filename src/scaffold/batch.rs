@@ -0,0 +1,229 @@
+//! Proving many inputs against the same circuit, for `prove --batch`.
+//!
+//! The trusted setup, pinning and proving key are loaded once, then each input file is proven independently, optionally on several worker threads.
+//! A failure on one input (a missing or invalid input file, or a panic in the circuit function) is recorded in the manifest and does not stop the batch.
+//! Panics can only be caught if the binary is not built with `panic = "abort"`, as the release profile of this crate is.
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use axiom_eth::util::{circuit::PreCircuit, Halo2ConfigPinning};
+use halo2_base::{
+    gates::builder::CircuitBuilderStage,
+    halo2_proofs::{
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        plonk::{Circuit, ProvingKey},
+        poly::kzg::commitment::ParamsKZG,
+        SerdeFormat,
+    },
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    cmd::Cli, degree::Degree, gen_snark, instances, load_params, proof::ProofOptions, read_pinning,
+    write_snark, CircuitPaths, ScaffoldError,
+};
+
+/// Summary of a batch, written to `{name}.batch.json` in the data directory.
+#[derive(Debug, Serialize)]
+pub struct BatchManifest {
+    pub name: String,
+    pub proved: usize,
+    pub failed: usize,
+    pub total_seconds: f64,
+    pub entries: Vec<BatchEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchEntry {
+    pub input: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snark: Option<PathBuf>,
+    pub seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Finds the input files of a batch. `pattern` is either a directory, in which case all files in it are used,
+/// or a path whose file name may contain the wildcards `*` and `?`, such as `queries/*.in`. Files are sorted by path.
+pub fn batch_inputs(pattern: &Path) -> Result<Vec<PathBuf>, ScaffoldError> {
+    let (dir, file_pattern) = if pattern.is_dir() {
+        (pattern, "*".to_string())
+    } else {
+        let dir = pattern.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let file_pattern = pattern.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        (dir.unwrap_or_else(|| Path::new(".")), file_pattern)
+    };
+    let mut inputs = vec![];
+    let read_error = |source| ScaffoldError::Read { path: dir.to_path_buf(), source };
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_file() && wildcard_match(&file_pattern, &file_name) {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// Matches `name` against `pattern`, where `*` matches any sequence of characters and `?` matches any single character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // matches[j] is true if the pattern read so far matches name[..j]
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+    for p in pattern {
+        let prev = matches.clone();
+        matches[0] = prev[0] && p == '*';
+        for j in 1..=name.len() {
+            matches[j] = match p {
+                '*' => matches[j - 1] || prev[j],
+                '?' => prev[j - 1],
+                c => prev[j - 1] && name[j - 1] == c,
+            };
+        }
+    }
+    matches[name.len()]
+}
+
+/// Proves each of `inputs` with the circuit created by `make_precircuit`, using `jobs` worker threads and the transcript and multi-open scheme of `options`,
+/// and writes the snarks and the manifest to `paths`.
+/// Keygen must have been run for the circuit, and `params` must have the degree recorded in its pinning.
+pub fn prove_batch<T, P>(
+    make_precircuit: impl Fn(T) -> P + Sync,
+    inputs: &[PathBuf],
+    params: &ParamsKZG<Bn256>,
+    paths: &CircuitPaths,
+    jobs: usize,
+    options: ProofOptions,
+) -> Result<BatchManifest, ScaffoldError>
+where
+    T: DeserializeOwned,
+    P: PreCircuit,
+    P::Pinning: DeserializeOwned,
+{
+    let start = Instant::now();
+    // loads the circuit configuration so the proving key can be read
+    read_pinning::<P::Pinning>(paths)?.set_var();
+    let pk = read_pk_for(&paths.pk(), |precircuit: P| {
+        precircuit.create_circuit(CircuitBuilderStage::Prover, None, params)
+    })?;
+
+    let next = AtomicUsize::new(0);
+    let entries = Mutex::new(Vec::with_capacity(inputs.len()));
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(i) else { break };
+                let input_start = Instant::now();
+                let snark_path = paths.batch_snark(input);
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let f = File::open(input).map_err(|source| ScaffoldError::MissingInput {
+                        path: input.clone(),
+                        source,
+                    })?;
+                    let private_inputs =
+                        serde_json::from_reader(BufReader::new(f)).map_err(|source| {
+                            ScaffoldError::InvalidInput { path: input.clone(), source }
+                        })?;
+                    let pinning = read_pinning::<P::Pinning>(paths)?;
+                    let circuit = make_precircuit(private_inputs).create_circuit(
+                        CircuitBuilderStage::Prover,
+                        Some(pinning),
+                        params,
+                    );
+                    let snark = gen_snark(params, &pk, circuit, options)?;
+                    write_snark(&snark_path, &snark)?;
+                    options.write_for(&snark_path)?;
+                    instances::write_instances(&paths.batch_instances(input), &snark.instances)
+                }));
+                let error = match result {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(_) => Some("circuit panicked".to_string()),
+                };
+                let seconds = input_start.elapsed().as_secs_f64();
+                match &error {
+                    Some(error) => println!("Failed to prove {input:?}: {error}"),
+                    None => {
+                        println!("Snark for {input:?} written to: {snark_path:?} ({seconds:.1}s)")
+                    }
+                }
+                let snark = error.is_none().then_some(snark_path);
+                let entry = BatchEntry { input: input.clone(), snark, seconds, error };
+                entries.lock().unwrap().push((i, entry));
+            });
+        }
+    });
+
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by_key(|(i, _)| *i);
+    let entries = entries.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>();
+    let failed = entries.iter().filter(|entry| entry.error.is_some()).count();
+    let manifest = BatchManifest {
+        name: paths.name.clone(),
+        proved: entries.len() - failed,
+        failed,
+        total_seconds: start.elapsed().as_secs_f64(),
+        entries,
+    };
+    let manifest_path = paths.batch_manifest();
+    let writer =
+        BufWriter::new(File::create(&manifest_path).map_err(ScaffoldError::io(&manifest_path))?);
+    serde_json::to_writer_pretty(writer, &manifest)
+        .map_err(|e| ScaffoldError::io(&manifest_path)(e.into()))?;
+    Ok(manifest)
+}
+
+/// Reads the proving key for the circuit type created by `_create_circuit`, which is never called, so that no circuit needs to be created up front.
+fn read_pk_for<P, C: Circuit<Fr>>(
+    path: &Path,
+    _create_circuit: impl FnOnce(P) -> C,
+) -> Result<ProvingKey<G1Affine>, ScaffoldError> {
+    let f = File::open(path)
+        .map_err(|source| ScaffoldError::MissingKey { path: path.to_path_buf(), source })?;
+    ProvingKey::read::<_, C>(&mut BufReader::new(f), SerdeFormat::RawBytesUnchecked)
+        .map_err(|source| ScaffoldError::KeyMismatch { path: path.to_path_buf(), source })
+}
+
+/// Runs `prove --batch`: proves every input file matched by `pattern`, relative to the data directory, with the circuit created by `make_precircuit`.
+pub fn run_batch_cli<T, P>(
+    make_precircuit: impl Fn(T) -> P + Sync,
+    cli: &Cli,
+    pattern: &Path,
+    jobs: usize,
+) -> Result<BatchManifest, ScaffoldError>
+where
+    T: DeserializeOwned,
+    P: PreCircuit,
+    P::Pinning: DeserializeOwned,
+{
+    let paths = CircuitPaths::from_cli(cli);
+    let k = match cli.degree {
+        Degree::Fixed(k) => k,
+        Degree::Auto => read_pinning::<P::Pinning>(&paths)?.degree(),
+    };
+    let params = load_params(cli.params_path.as_deref(), k)?;
+    let inputs = batch_inputs(&paths.data_path.join(pattern))?;
+    let options = ProofOptions::from_cli(cli);
+    let manifest = prove_batch(make_precircuit, &inputs, &params, &paths, jobs, options)?;
+    println!(
+        "Proved {} of {} inputs, manifest written to: {:?}",
+        manifest.proved,
+        manifest.entries.len(),
+        paths.batch_manifest()
+    );
+    Ok(manifest)
+}
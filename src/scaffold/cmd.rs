@@ -1,19 +1,74 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
-#[derive(Clone, Copy, Debug, Subcommand)]
+use super::{
+    degree::Degree,
+    proof::{MultiOpenKind, TranscriptKind},
+    CircuitKind,
+};
+
+#[derive(Clone, Debug, Subcommand)]
 pub enum SnarkCmd {
     /// Run the mock prover
     Mock,
     /// Generate new proving & verifying keys
     Keygen,
     /// Generate a new proof
-    Prove,
+    Prove {
+        /// Prove every input file in this directory, or matching this pattern such as `queries/*.in`, relative to the data directory
+        #[arg(long = "batch")]
+        batch: Option<PathBuf>,
+        /// Number of inputs of a batch to prove in parallel
+        #[arg(long = "jobs", default_value_t = 1)]
+        jobs: usize,
+    },
     /// Verify a proof
-    Verify,
+    Verify {
+        /// Also check that the public instances of the snark are the ones in this JSON file, in the format of the `{name}.instances.json` written by `prove`
+        #[arg(long = "expect-instances")]
+        expect_instances: Option<PathBuf>,
+    },
+    /// Print the shape and size of the circuit without running keygen
+    Stats {
+        /// Print the report as JSON instead of a table
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Run a step of the aggregation circuit that aggregates snarks into a single snark
+    Aggregate {
+        step: AggregateCmd,
+        /// Snarks to aggregate, located in the data directory. Defaults to `{name}.snark`
+        #[arg(long = "snarks", num_args = 1..)]
+        snarks: Vec<PathBuf>,
+    },
 }
 
 impl std::fmt::Display for SnarkCmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mock => write!(f, "mock"),
+            Self::Keygen => write!(f, "keygen"),
+            Self::Prove { .. } => write!(f, "prove"),
+            Self::Verify { .. } => write!(f, "verify"),
+            Self::Stats { .. } => write!(f, "stats"),
+            Self::Aggregate { step, .. } => write!(f, "aggregate {step}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum AggregateCmd {
+    /// Run the mock prover on the aggregation circuit
+    Mock,
+    /// Generate proving & verifying keys for the aggregation circuit
+    Keygen,
+    /// Generate a new aggregation proof
+    Prove,
+    /// Verify an aggregation proof, including its accumulator
+    Verify,
+}
+
+impl std::fmt::Display for AggregateCmd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Mock => write!(f, "mock"),
@@ -32,12 +87,53 @@ pub struct Cli {
     pub command: SnarkCmd,
     #[arg(short, long = "name")]
     pub name: String,
+    /// Degree `k` of the circuit, or `auto` to choose it from the number of cells during `mock` and `keygen`. With `auto`, `prove` and `verify` read the degree from the pinning
     #[arg(short = 'k', long = "degree")]
-    pub degree: u32,
+    pub degree: Degree,
     #[arg(short, long = "input")]
     pub input_path: Option<PathBuf>,
+    /// Number of bits of the lookup table used for range checks, which must be less than the degree. Only used by `mock` and `keygen`; `prove` and `verify` read it from the pinning
+    #[arg(long = "lookup-bits")]
+    pub lookup_bits: Option<usize>,
+    /// Number of rows reserved at the bottom of the circuit for blinding factors. Only used by `mock` and `keygen`
+    #[arg(long = "minimum-rows")]
+    pub minimum_rows: Option<usize>,
+    /// Trusted setup to use, either halo2 `ParamsKZG` raw bytes or a Perpetual Powers of Tau `.ptau` file. If not specified, an unsafe setup is generated locally
+    #[arg(long = "params")]
+    pub params_path: Option<PathBuf>,
+    /// During `keygen`, also write a Yul verifier contract. During `prove`, instead create a proof for the EVM verifier, write it as calldata, and check it in a local EVM.
     #[arg(long = "create-contract")]
     pub create_contract: bool,
+    /// Hash function of the Fiat-Shamir transcript used by `prove`. Only snarks with a `poseidon` transcript can be aggregated. Ignored with `--create-contract`, which always uses `keccak`
+    #[arg(long = "transcript", value_enum, default_value_t = TranscriptKind::Poseidon)]
+    pub transcript: TranscriptKind,
+    /// Multi-open scheme used by `prove`. Only snarks proven with `shplonk` can be aggregated. Ignored with `--create-contract`
+    #[arg(long = "multiopen", value_enum, default_value_t = MultiOpenKind::Shplonk)]
+    pub multiopen: MultiOpenKind,
+    /// During `mock`, also write the report of unsatisfied constraints as JSON to this file
+    #[arg(long = "mock-report")]
+    pub mock_report: Option<PathBuf>,
+    #[arg(short, long = "config-path")]
+    pub config_path: Option<PathBuf>,
+    #[arg(short, long = "data-path")]
+    pub data_path: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+/// Verifies a snark using only its verifying key, circuit pinning and metadata file `{name}.snark.json`, without the circuit or its private inputs.
+pub struct VerifyCli {
+    #[arg(short, long = "name")]
+    pub name: String,
+    /// Scaffold that created the circuit
+    #[arg(long = "kind", value_enum, default_value_t = CircuitKind::Range)]
+    pub kind: CircuitKind,
+    /// Trusted setup used for keygen, either halo2 `ParamsKZG` raw bytes or a Perpetual Powers of Tau `.ptau` file. If not specified, the unsafe setup generated locally is used
+    #[arg(long = "params")]
+    pub params_path: Option<PathBuf>,
+    /// Also check that the public instances of the snark are the ones in this JSON file, in the format of the `{name}.instances.json` written by `prove`
+    #[arg(long = "expect-instances")]
+    pub expect_instances: Option<PathBuf>,
     #[arg(short, long = "config-path")]
     pub config_path: Option<PathBuf>,
     #[arg(short, long = "data-path")]
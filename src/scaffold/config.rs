@@ -0,0 +1,221 @@
+//! Configuration of the circuits created by [`super::run`], [`super::run_builder`], and their [`super::run_rlc`] counterparts.
+//!
+//! The lookup table size and the number of rows reserved for blinding factors are chosen on the command line during `mock` and `keygen`,
+//! and saved in the circuit pinning together with the number of columns. `prove` and `verify` read them back from the pinning.
+//!
+//! `Circuit::configure` takes no arguments, so the configuration of the circuit being created is kept in a thread local instead of process environment variables.
+//! Circuits created on different threads do not interfere with each other.
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use axiom_eth::{rlp::builder::RlcThreadBreakPoints, util::Halo2ConfigPinning};
+use halo2_base::gates::builder::{FlexGateConfigParams, MultiPhaseThreadBreakPoints};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{cmd::Cli, ScaffoldError};
+
+/// Default number of rows reserved at the bottom of the circuit for blinding factors. This depends on the circuit itself, but 9 usually works.
+pub const DEFAULT_MINIMUM_ROWS: usize = 9;
+
+/// Circuit options specified by the user. Options that are `None` fall back to the default of the scaffold creating the circuit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CircuitConfig {
+    /// Number of bits of the lookup table used for range checks
+    pub lookup_bits: Option<usize>,
+    /// Number of rows reserved at the bottom of the circuit for blinding factors
+    pub minimum_rows: Option<usize>,
+}
+
+impl CircuitConfig {
+    pub fn from_cli(cli: &Cli) -> Self {
+        Self { lookup_bits: cli.lookup_bits, minimum_rows: cli.minimum_rows }
+    }
+}
+
+/// The parameters of a circuit created by [`super::run_builder`], needed to recreate its constraint system.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScaffoldConfigParams {
+    pub degree: u32,
+    pub num_advice_per_phase: Vec<usize>,
+    pub num_lookup_advice_per_phase: Vec<usize>,
+    pub num_fixed: usize,
+    /// The lookup table has `2^lookup_bits` rows. If `0`, the circuit does not use range checks
+    pub lookup_bits: usize,
+    pub minimum_rows: usize,
+    /// Number of phase 1 advice columns of the `RlcChip`, only used by circuits created with `run_rlc`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub num_rlc_columns: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl ScaffoldConfigParams {
+    /// Records the number of columns chosen by `GateThreadBuilder::config`.
+    pub fn with_columns(self, flex_gate_params: FlexGateConfigParams) -> Self {
+        Self {
+            num_advice_per_phase: flex_gate_params.num_advice_per_phase,
+            num_lookup_advice_per_phase: flex_gate_params.num_lookup_advice_per_phase,
+            num_fixed: flex_gate_params.num_fixed,
+            ..self
+        }
+    }
+}
+
+/// Reads the circuit pinning at `path`, returning an error if it is missing or is not a valid pinning of type `P`.
+pub fn read_pinning<P: DeserializeOwned>(path: &Path) -> Result<P, ScaffoldError> {
+    let f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(ScaffoldError::MissingPinning { path: path.to_path_buf() })
+        }
+        Err(e) => {
+            return Err(ScaffoldError::InvalidPinning {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            })
+        }
+    };
+    serde_json::from_reader(BufReader::new(f)).map_err(|e| ScaffoldError::InvalidPinning {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScaffoldConfigPinning {
+    pub params: ScaffoldConfigParams,
+    pub break_points: MultiPhaseThreadBreakPoints,
+}
+
+impl Halo2ConfigPinning for ScaffoldConfigPinning {
+    type BreakPoints = MultiPhaseThreadBreakPoints;
+
+    /// Panics if the pinning cannot be read, since the trait method cannot return an error. The scaffold itself uses [`read_pinning`], which does not panic.
+    fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        read_pinning(path.as_ref()).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Loads the parameters into the configuration of the current thread (not into environment variables, despite the name of the trait method).
+    fn set_var(&self) {
+        set_current(self.params.clone());
+    }
+
+    fn break_points(self) -> MultiPhaseThreadBreakPoints {
+        self.break_points
+    }
+
+    fn from_var(break_points: MultiPhaseThreadBreakPoints) -> Self {
+        Self { params: current(), break_points }
+    }
+
+    fn degree(&self) -> u32 {
+        self.params.degree
+    }
+}
+
+/// Pinning of the circuits created by [`super::run_rlc`], which have the same parameters but also break points for the RLC threads.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RlcConfigPinning {
+    pub params: ScaffoldConfigParams,
+    pub break_points: RlcThreadBreakPoints,
+}
+
+impl Halo2ConfigPinning for RlcConfigPinning {
+    type BreakPoints = RlcThreadBreakPoints;
+
+    /// Panics if the pinning cannot be read, since the trait method cannot return an error. The scaffold itself uses [`read_pinning`], which does not panic.
+    fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        read_pinning(path.as_ref()).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Loads the parameters into the configuration of the current thread.
+    fn set_var(&self) {
+        set_current(self.params.clone());
+    }
+
+    fn break_points(self) -> RlcThreadBreakPoints {
+        self.break_points
+    }
+
+    fn from_var(break_points: RlcThreadBreakPoints) -> Self {
+        Self { params: current(), break_points }
+    }
+
+    fn degree(&self) -> u32 {
+        self.params.degree
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<ScaffoldConfigParams> = RefCell::new(ScaffoldConfigParams::default());
+}
+
+/// The parameters of the circuit most recently created or loaded from a pinning on this thread.
+pub fn current() -> ScaffoldConfigParams {
+    CURRENT.with(|params| params.borrow().clone())
+}
+
+pub fn set_current(params: ScaffoldConfigParams) {
+    CURRENT.with(|current| *current.borrow_mut() = params);
+}
+
+/// Number of bits of the lookup table of the circuit being created. Use this to create a `RangeChip` inside functions passed to [`super::run`].
+pub fn lookup_bits() -> usize {
+    CURRENT.with(|params| params.borrow().lookup_bits)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use halo2_base::{
+        gates::builder::CircuitBuilderStage, halo2_proofs::halo2curves::bn256::Fr,
+        utils::fs::gen_srs,
+    };
+
+    use super::{read_pinning, CircuitConfig, ScaffoldConfigPinning};
+    use crate::scaffold::{mock, pre_run_builder_on_inputs, ScaffoldError, ScaffoldPreCircuit};
+
+    #[test]
+    fn test_read_pinning() {
+        let path = env::temp_dir().join(format!("scaffold-pinning-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let result = read_pinning::<ScaffoldConfigPinning>(&path);
+        assert!(matches!(result, Err(ScaffoldError::MissingPinning { .. })));
+
+        fs::write(&path, r#"{ "params": { "degree": 10 }"#).unwrap();
+        let result = read_pinning::<ScaffoldConfigPinning>(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(ScaffoldError::InvalidPinning { .. })));
+    }
+
+    #[test]
+    fn test_check_create() {
+        let precircuit = || {
+            pre_run_builder_on_inputs(
+                |builder, x: u64, make_public| {
+                    let x = builder.main(0).load_witness(Fr::from(x));
+                    make_public.push(x);
+                },
+                7,
+                CircuitConfig { lookup_bits: Some(12), ..Default::default() },
+            )
+        };
+        let result = mock(precircuit(), &gen_srs(10));
+        assert!(matches!(
+            result,
+            Err(ScaffoldError::LookupBitsTooLarge { lookup_bits: 12, degree: 10 })
+        ));
+        assert!(precircuit().check_create(CircuitBuilderStage::Mock, None, 13).is_ok());
+        assert!(matches!(
+            precircuit().check_create(CircuitBuilderStage::Prover, None, 13),
+            Err(ScaffoldError::MissingBreakPoints)
+        ));
+    }
+}
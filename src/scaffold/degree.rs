@@ -0,0 +1,115 @@
+//! Automatic selection of the circuit degree `k` from the number of cells used by witness generation.
+//!
+//! For a fixed execution trace, a smaller `k` means more columns. Proving time is roughly proportional to `k * 2^k` per column,
+//! plus a per-column independent cost for the permutation argument, quotient polynomial, and lookup table.
+//! We pick the `k` minimizing this estimate among all degrees where the circuit fits.
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use halo2_base::{gates::builder::GateThreadBuilder, utils::ScalarField};
+
+use super::ScaffoldError;
+
+/// The largest degree considered, since the unsafe trusted setup and keygen become impractical beyond this.
+pub const MAX_DEGREE: u32 = 24;
+/// Smallest degree considered.
+pub const MIN_DEGREE: u32 = 6;
+/// Estimated cost of the columns every circuit has regardless of the number of cells, in units of one advice column.
+const BASE_COLUMNS: usize = 8;
+/// Estimated cost of a lookup advice column relative to an advice column, for the extra commitments of the lookup argument.
+const LOOKUP_COLUMN_COST: usize = 3;
+
+/// The degree `k` of the circuit, which has `2^k` rows. `auto` chooses the degree from the number of cells used by the circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Degree {
+    Fixed(u32),
+    Auto,
+}
+
+impl Degree {
+    /// The degree, unless it should be chosen automatically.
+    pub fn fixed(self) -> Option<u32> {
+        match self {
+            Self::Fixed(k) => Some(k),
+            Self::Auto => None,
+        }
+    }
+}
+
+impl FromStr for Degree {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(Self::Auto);
+        }
+        s.parse().map(Self::Fixed).map_err(|_| format!("expected a number or `auto`, found `{s}`"))
+    }
+}
+
+impl fmt::Display for Degree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(k) => write!(f, "{k}"),
+            Self::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Number of cells of each type used by a circuit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CellCounts {
+    pub advice: usize,
+    /// Advice cells that are range checked with the lookup table, each of which is copied to a lookup advice column
+    pub lookup: usize,
+    /// Distinct constants, each of which is stored once in a fixed column
+    pub fixed: usize,
+    pub instances: usize,
+}
+
+impl CellCounts {
+    pub fn from_builder<F: ScalarField>(
+        builder: &GateThreadBuilder<F>,
+        num_instances: usize,
+    ) -> Self {
+        let contexts = builder.threads.iter().flatten();
+        let advice = contexts.clone().map(|ctx| ctx.advice.len()).sum();
+        let lookup = contexts.clone().map(|ctx| ctx.cells_to_lookup.len()).sum();
+        let fixed = contexts
+            .flat_map(|ctx| ctx.constant_equality_constraints.iter().map(|(c, _)| *c))
+            .collect::<HashSet<F>>()
+            .len();
+        Self { advice, lookup, fixed, instances: num_instances }
+    }
+
+    /// Estimated proving cost at degree `k`, or `None` if the circuit does not fit.
+    pub fn cost(&self, k: u32, lookup_bits: usize, minimum_rows: usize) -> Option<usize> {
+        let rows = 1usize << k;
+        // the lookup table needs `2^lookup_bits` usable rows
+        if rows <= minimum_rows || (lookup_bits > 0 && lookup_bits >= k as usize) {
+            return None;
+        }
+        let usable_rows = rows - minimum_rows;
+        if self.instances > usable_rows {
+            return None;
+        }
+        let num_advice = (self.advice + usable_rows - 1) / usable_rows;
+        let num_lookup_advice = (self.lookup + usable_rows - 1) / usable_rows;
+        let num_fixed = (self.fixed + rows - 1) / rows;
+        let columns =
+            num_advice + LOOKUP_COLUMN_COST * num_lookup_advice + num_fixed + BASE_COLUMNS;
+        Some(columns * rows * k as usize)
+    }
+}
+
+/// Chooses the degree in `[MIN_DEGREE, MAX_DEGREE]` with the smallest estimated proving cost, preferring the smaller degree on ties.
+pub fn choose_degree(
+    cells: &CellCounts,
+    lookup_bits: usize,
+    minimum_rows: usize,
+) -> Result<u32, ScaffoldError> {
+    (MIN_DEGREE..=MAX_DEGREE)
+        .filter_map(|k| Some((cells.cost(k, lookup_bits, minimum_rows)?, k)))
+        .min()
+        .map(|(_, k)| k)
+        .ok_or(ScaffoldError::NoDegreeFits { max_degree: MAX_DEGREE })
+}
@@ -0,0 +1,178 @@
+//! Errors returned by the programmatic scaffold API, so that embedding applications do not have to rely on panics.
+use std::{error::Error, fmt, io, path::PathBuf};
+
+use halo2_base::halo2_proofs::plonk;
+
+use super::{proof::ProofOptions, report::MockReport};
+
+#[derive(Debug)]
+pub enum ScaffoldError {
+    /// The input file could not be opened.
+    MissingInput { path: PathBuf, source: io::Error },
+    /// The input file is not valid JSON for the circuit's input type.
+    InvalidInput { path: PathBuf, source: serde_json::Error },
+    /// The circuit pinning has not been generated yet; run keygen first.
+    MissingPinning { path: PathBuf },
+    /// The circuit pinning could not be read or is not a valid pinning for this circuit.
+    InvalidPinning { path: PathBuf, reason: String },
+    /// The circuit was created for the prover without the break points of its pinning.
+    MissingBreakPoints,
+    /// The lookup table of `2^lookup_bits` rows does not fit in a circuit of the given degree.
+    LookupBitsTooLarge { lookup_bits: usize, degree: u32 },
+    /// The SRS file could not be opened.
+    MissingParams { path: PathBuf, source: io::Error },
+    /// The SRS file is malformed, too small, or inconsistent.
+    InvalidParams { path: PathBuf, reason: String },
+    /// The proving or verifying key could not be found.
+    MissingKey { path: PathBuf, source: io::Error },
+    /// The proving or verifying key could not be read as a key for this circuit.
+    KeyMismatch { path: PathBuf, source: io::Error },
+    /// The snark could not be read.
+    MissingSnark { path: PathBuf, source: bincode::Error },
+    /// An output file could not be written.
+    Io { path: PathBuf, source: io::Error },
+    /// A file or directory other than the inputs, keys, SRS and snark could not be read, e.g. the directory of a batch.
+    Read { path: PathBuf, source: io::Error },
+    /// The mock prover found constraints that are not satisfied by the witness.
+    Unsatisfied(MockReport),
+    /// The Halo2 backend failed during key generation or proving.
+    Halo2(plonk::Error),
+    /// The verifier rejected the proof.
+    VerificationFailed(plonk::Error),
+    /// The verifier contract rejected the proof in the local EVM.
+    EvmVerificationFailed,
+    /// The accumulator exposed by an aggregation snark does not pass the pairing check.
+    InvalidAccumulator,
+    /// `--degree auto` was used with a circuit that does not support automatic degree selection.
+    AutoDegreeUnsupported,
+    /// The circuit does not fit in any degree up to `max_degree`.
+    NoDegreeFits { max_degree: u32 },
+    /// `prove --batch` was used with a circuit that was given its inputs directly.
+    BatchUnsupported,
+    /// `prove --batch` was used with `--jobs` above 1 for a circuit that can only be configured on one thread at a time.
+    ParallelBatchUnsupported,
+    /// The expected public instances file could not be read or contains an invalid field element.
+    InvalidInstances { path: PathBuf, reason: String },
+    /// The public instances of the snark differ from the expected ones.
+    UnexpectedInstances { path: PathBuf, reason: String },
+    /// The metadata recording how a snark was proven could not be read.
+    InvalidSnarkMetadata { path: PathBuf, reason: String },
+    /// A snark to aggregate was not proven with a Poseidon transcript and SHPLONK.
+    IncompatibleSnark { path: PathBuf, options: ProofOptions },
+}
+
+impl fmt::Display for ScaffoldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingInput { path, source } => {
+                write!(f, "Input file not found at {path:?}: {source}")
+            }
+            Self::InvalidInput { path, source } => {
+                write!(f, "Input file {path:?} is not valid JSON for this circuit: {source}")
+            }
+            Self::MissingPinning { path } => {
+                write!(f, "Circuit pinning not found at {path:?}. Run keygen first")
+            }
+            Self::InvalidPinning { path, reason } => {
+                write!(f, "Invalid circuit pinning {path:?}: {reason}")
+            }
+            Self::MissingBreakPoints => {
+                write!(f, "Creating a circuit for the prover needs the break points of its pinning")
+            }
+            Self::LookupBitsTooLarge { lookup_bits, degree } => {
+                write!(
+                    f,
+                    "Lookup bits ({lookup_bits}) must be less than the degree ({degree}). Increase -k or decrease --lookup-bits"
+                )
+            }
+            Self::MissingParams { path, source } => {
+                write!(f, "SRS not found at {path:?}: {source}")
+            }
+            Self::InvalidParams { path, reason } => write!(f, "Invalid SRS at {path:?}: {reason}"),
+            Self::MissingKey { path, source } => write!(f, "Key not found at {path:?}: {source}"),
+            Self::KeyMismatch { path, source } => {
+                write!(f, "Key at {path:?} does not match this circuit: {source}")
+            }
+            Self::MissingSnark { path, source } => {
+                write!(f, "Could not read snark at {path:?}: {source}")
+            }
+            Self::Io { path, source } => write!(f, "Failed to write {path:?}: {source}"),
+            Self::Read { path, source } => write!(f, "Failed to read {path:?}: {source}"),
+            Self::Unsatisfied(report) => write!(f, "{report}"),
+            Self::Halo2(e) => write!(f, "Halo2 error: {e:?}"),
+            Self::VerificationFailed(e) => write!(f, "Snark verification failed: {e:?}"),
+            Self::EvmVerificationFailed => write!(f, "EVM verifier contract rejected the proof"),
+            Self::InvalidAccumulator => write!(f, "Aggregation snark has an invalid accumulator"),
+            Self::AutoDegreeUnsupported => {
+                write!(
+                    f,
+                    "This circuit does not support `--degree auto`. Specify the degree with -k"
+                )
+            }
+            Self::NoDegreeFits { max_degree } => {
+                write!(f, "Circuit does not fit in any degree up to {max_degree}")
+            }
+            Self::BatchUnsupported => {
+                write!(f, "`prove --batch` needs the circuit function; use `run` or `run_builder`")
+            }
+            Self::ParallelBatchUnsupported => {
+                write!(
+                    f,
+                    "`prove --batch` of circuits created with `run_eth` only supports `--jobs 1`"
+                )
+            }
+            Self::InvalidInstances { path, reason } => {
+                write!(f, "Invalid public instances file {path:?}: {reason}")
+            }
+            Self::UnexpectedInstances { path, reason } => {
+                write!(f, "Snark does not prove the public instances in {path:?}: {reason}")
+            }
+            Self::InvalidSnarkMetadata { path, reason } => {
+                write!(f, "Invalid snark metadata {path:?}: {reason}")
+            }
+            Self::IncompatibleSnark { path, options } => {
+                write!(
+                    f,
+                    "Snark {path:?} was proven with a {options}, but only snarks proven with a poseidon transcript with shplonk can be aggregated"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ScaffoldError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MissingInput { source, .. }
+            | Self::MissingParams { source, .. }
+            | Self::MissingKey { source, .. }
+            | Self::KeyMismatch { source, .. }
+            | Self::Io { source, .. }
+            | Self::Read { source, .. } => Some(source),
+            Self::InvalidInput { source, .. } => Some(source),
+            Self::MissingSnark { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<plonk::Error> for ScaffoldError {
+    fn from(e: plonk::Error) -> Self {
+        Self::Halo2(e)
+    }
+}
+
+impl ScaffoldError {
+    /// Exit code of command line binaries: 2 if the mock prover found unsatisfied constraints, 1 for all other errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Unsatisfied(_) => 2,
+            _ => 1,
+        }
+    }
+
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+        move |source| Self::Io { path, source }
+    }
+}
@@ -0,0 +1,159 @@
+//! Public instances of a snark as JSON, written next to each snark by `prove` and checked by `verify --expect-instances`.
+//!
+//! The instances are stored per instance column, each field element both in decimal and in big-endian hex, so that what was proven can be read without deserializing the snark.
+//! When reading expected instances, each field element may be given either as such an object or as a single decimal or `0x`-prefixed hex string.
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use halo2_base::{halo2_proofs::halo2curves::bn256::Fr, utils::fe_to_biguint};
+use serde::{Deserialize, Serialize};
+
+use super::ScaffoldError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstancesFile {
+    /// Public instances of each instance column, in the order they were pushed to `make_public`
+    pub instances: Vec<Vec<InstanceValue>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InstanceValue {
+    Both { decimal: String, hex: String },
+    Str(String),
+}
+
+impl InstanceValue {
+    pub fn new(value: &Fr) -> Self {
+        let value = fe_to_biguint(value);
+        Self::Both { decimal: value.to_string(), hex: format!("0x{}", value.to_str_radix(16)) }
+    }
+
+    /// The field element, or an error message if it is not a valid decimal or hex number.
+    fn parse(&self) -> Result<Fr, String> {
+        match self {
+            Self::Both { decimal, hex } => {
+                let value = parse_field_element(decimal)?;
+                if parse_field_element(hex)? != value {
+                    return Err(format!("decimal {decimal} and hex {hex} are different values"));
+                }
+                Ok(value)
+            }
+            Self::Str(s) => parse_field_element(s),
+        }
+    }
+}
+
+/// Parses a decimal or `0x`-prefixed hex number, which must be less than the field size.
+/// Numbers that are not are rejected instead of reduced, since e.g. `p + 5` would otherwise match an instance equal to 5.
+fn parse_field_element(s: &str) -> Result<Fr, String> {
+    let (digits, radix) = match s.strip_prefix("0x") {
+        Some(hex) => (hex, 16),
+        None => (s, 10),
+    };
+    if digits.is_empty() {
+        return Err(format!("`{s}` is not a number"));
+    }
+    let value = digits.chars().try_fold(Fr::zero(), |acc, c| {
+        let digit = c.to_digit(radix).ok_or_else(|| format!("`{s}` is not a number"))?;
+        Ok(acc * Fr::from(radix as u64) + Fr::from(digit as u64))
+    })?;
+    // the digits were reduced modulo the field size, so they only print back the same if the number is less than it
+    let digits = digits.trim_start_matches('0').to_ascii_lowercase();
+    let canonical = fe_to_biguint(&value).to_str_radix(radix);
+    if digits != canonical && !(digits.is_empty() && canonical == "0") {
+        return Err(format!("`{s}` is not less than the field size"));
+    }
+    Ok(value)
+}
+
+/// Writes the public instances of a snark as JSON to `path`.
+pub fn write_instances(path: &Path, instances: &[Vec<Fr>]) -> Result<(), ScaffoldError> {
+    let file = InstancesFile {
+        instances: instances
+            .iter()
+            .map(|column| column.iter().map(InstanceValue::new).collect())
+            .collect(),
+    };
+    let writer = BufWriter::new(File::create(path).map_err(ScaffoldError::io(path))?);
+    serde_json::to_writer_pretty(writer, &file).map_err(|e| ScaffoldError::io(path)(e.into()))
+}
+
+/// Reads public instances written by [`write_instances`], or written by hand in the same format.
+pub fn read_instances(path: &Path) -> Result<Vec<Vec<Fr>>, ScaffoldError> {
+    let invalid =
+        |reason: String| ScaffoldError::InvalidInstances { path: path.to_path_buf(), reason };
+    let json = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    let file: InstancesFile = serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))?;
+    file.instances
+        .iter()
+        .map(|column| column.iter().map(InstanceValue::parse).collect::<Result<Vec<_>, _>>())
+        .collect::<Result<_, _>>()
+        .map_err(invalid)
+}
+
+/// Checks that `instances`, the public instances of a verified snark, are the ones claimed in the file at `path`.
+pub fn check_instances(path: &Path, instances: &[Vec<Fr>]) -> Result<(), ScaffoldError> {
+    let expected = read_instances(path)?;
+    let unexpected =
+        |reason: String| ScaffoldError::UnexpectedInstances { path: path.to_path_buf(), reason };
+    if expected.len() != instances.len() {
+        return Err(unexpected(format!(
+            "snark has {} instance columns, expected {}",
+            instances.len(),
+            expected.len()
+        )));
+    }
+    for (column, (expected, actual)) in expected.iter().zip(instances).enumerate() {
+        if expected.len() != actual.len() {
+            return Err(unexpected(format!(
+                "snark has {} public instances in column {column}, expected {}",
+                actual.len(),
+                expected.len()
+            )));
+        }
+        if let Some(i) =
+            expected.iter().zip(actual).position(|(expected, actual)| expected != actual)
+        {
+            return Err(unexpected(format!(
+                "public instance {i} in column {column} is {}, expected {}",
+                fe_to_biguint(&actual[i]),
+                fe_to_biguint(&expected[i])
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::{halo2_proofs::halo2curves::bn256::Fr, utils::fe_to_biguint};
+
+    use super::parse_field_element;
+
+    #[test]
+    fn test_parse_field_element() {
+        assert_eq!(parse_field_element("0"), Ok(Fr::zero()));
+        assert_eq!(parse_field_element("0x0"), Ok(Fr::zero()));
+        assert_eq!(parse_field_element("007"), Ok(Fr::from(7)));
+        assert_eq!(parse_field_element("0xFf"), Ok(Fr::from(255)));
+        let max = fe_to_biguint(&-Fr::one());
+        assert_eq!(parse_field_element(&max.to_string()), Ok(-Fr::one()));
+        assert_eq!(parse_field_element(&format!("0x{}", max.to_str_radix(16))), Ok(-Fr::one()));
+
+        let modulus = max + 1u32;
+        for s in [
+            modulus.to_string(),
+            (modulus.clone() + 5u32).to_string(),
+            format!("0x{}", modulus.to_str_radix(16)),
+        ] {
+            assert!(parse_field_element(&s).is_err(), "{s} must be rejected");
+        }
+        for s in ["", "0x", "12a", "-1"] {
+            assert!(parse_field_element(s).is_err(), "{s} must be rejected");
+        }
+    }
+}
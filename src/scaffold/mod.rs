@@ -10,23 +10,19 @@ use axiom_eth::{
     },
 };
 use halo2_base::{
-    gates::builder::{
-        CircuitBuilderStage, GateThreadBuilder, MultiPhaseThreadBreakPoints, RangeCircuitBuilder,
-        RangeWithInstanceCircuitBuilder, RangeWithInstanceConfig,
+    gates::{
+        builder::{
+            CircuitBuilderStage, GateThreadBuilder, MultiPhaseThreadBreakPoints,
+            RangeCircuitBuilder, RangeWithInstanceCircuitBuilder, RangeWithInstanceConfig,
+        },
+        range::{RangeConfig, RangeStrategy},
     },
     halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner},
         dev::MockProver,
         halo2curves::bn256::{Bn256, Fr, G1Affine},
-        plonk::{verify_proof, Circuit, ConstraintSystem, Error, ProvingKey, VerifyingKey},
-        poly::{
-            commitment::{Params, ParamsProver},
-            kzg::{
-                commitment::{KZGCommitmentScheme, ParamsKZG},
-                multiopen::VerifierSHPLONK,
-                strategy::SingleStrategy,
-            },
-        },
+        plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error, ProvingKey, VerifyingKey},
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
         SerdeFormat,
     },
     utils::{fs::gen_srs, ScalarField},
@@ -34,45 +30,153 @@ use halo2_base::{
 };
 use serde::de::DeserializeOwned;
 use snark_verifier_sdk::{
-    halo2::{gen_snark_shplonk, read_snark, PoseidonTranscript},
-    read_pk, CircuitExt, NativeLoader,
+    evm::{evm_verify, gen_evm_proof_shplonk, gen_evm_verifier_shplonk, write_calldata},
+    snark_verifier::system::halo2::{compile, Config},
+    CircuitExt, Snark,
 };
 use std::{
-    env::{set_var, var},
     fs::{self, File},
-    io::{BufReader, BufWriter},
+    io::{self, BufReader, BufWriter},
+    panic,
     path::{Path, PathBuf},
 };
 
-use self::cmd::{Cli, SnarkCmd};
+use self::{
+    aggregation::AggregationScaffold,
+    cmd::{AggregateCmd, Cli, SnarkCmd},
+    config::{ScaffoldConfigParams, ScaffoldConfigPinning, DEFAULT_MINIMUM_ROWS},
+    degree::{CellCounts, Degree},
+    proof::ProofOptions,
+    report::MockReport,
+    stats::{BuilderStats, CircuitStats},
+};
+pub use self::{
+    config::{lookup_bits, CircuitConfig},
+    error::ScaffoldError,
+    verifier::{verify, CircuitKind},
+};
 
+pub mod aggregation;
+pub mod batch;
 pub mod cmd;
+pub mod config;
+pub mod degree;
+pub mod error;
+pub mod instances;
+pub mod proof;
+pub mod report;
+mod rlc;
+pub mod srs;
+pub mod stats;
+pub mod testing;
+pub mod verifier;
 ///! The functions below are generic scaffolding functions to create circuits with 'halo2-lib'
 
 pub struct CircuitScaffold<T, Fn> {
-    f: Fn,
-    private_inputs: T,
+    witness: Witness<T, Fn>,
+    config: CircuitConfig,
+}
+
+/// The execution trace of the circuit, which is generated before the circuit is created when the degree is chosen automatically.
+enum Witness<T, Fn> {
+    Pending { f: Fn, private_inputs: T },
+    Generated { builder: GateThreadBuilder<Fr>, assigned_instances: Vec<AssignedValue<Fr>> },
+}
+
+impl<T, Fn> Witness<T, Fn>
+where
+    Fn: FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+{
+    /// Runs witness generation if it has not been run yet.
+    fn generate(self, witness_gen_only: bool) -> (GateThreadBuilder<Fr>, Vec<AssignedValue<Fr>>) {
+        match self {
+            Self::Pending { f, private_inputs } => {
+                // we initiate a "thread builder". This is what keeps track of the execution trace of our program. If not in proving mode, it also keeps track of the ZK constraints.
+                let mut builder = GateThreadBuilder::new(witness_gen_only);
+                // builder.main(phase) gets a default "main" thread for the given phase. For most purposes we only need to think about phase 0
+                // we need a 64-bit number as input in this case
+                // while `some_algorithm_in_zk` was written generically for any field `F`, in practice we use the scalar field of the BN254 curve because that's what the proving system backend uses
+                let mut assigned_instances = vec![];
+                f(&mut builder, private_inputs, &mut assigned_instances);
+                (builder, assigned_instances)
+            }
+            Self::Generated { builder, assigned_instances } => (builder, assigned_instances),
+        }
+    }
+}
+
+/// Private inputs of a circuit that can be replaced by placeholder values during keygen, so that keys can be generated without an input file.
+pub trait KeygenInputs: DeserializeOwned {
+    /// Inputs to create the circuit with during keygen when there is no input file. Keygen only depends on the shape of the circuit,
+    /// so any inputs for which the circuit has the same constraints as for the real inputs will do.
+    fn keygen_inputs() -> Self;
 }
 
-pub fn run<T: DeserializeOwned>(
-    f: impl FnOnce(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+/// A [`PreCircuit`] created by this scaffold, whose verifying key can be read back from its pinning alone.
+pub trait ScaffoldPreCircuit: PreCircuit + Sized {
+    const KIND: CircuitKind;
+
+    /// Chooses the degree of the circuit from the number of cells it uses, for `--degree auto`.
+    /// Witness generation may be run here, in which case the returned pre-circuit reuses its result.
+    fn auto_degree(self) -> Result<(Self, u32), ScaffoldError> {
+        Err(ScaffoldError::AutoDegreeUnsupported)
+    }
+
+    /// Reports the shape of the circuit of degree `params.k()` without running keygen.
+    /// By default, only what is visible from the constraint system and the pinning is reported.
+    fn stats(self, params: &ParamsKZG<Bn256>) -> Result<CircuitStats, ScaffoldError> {
+        let circuit = self.create_circuit(CircuitBuilderStage::Mock, None, params);
+        stats::circuit_stats(&circuit, params.k(), |_| BuilderStats::default())
+    }
+
+    /// Number of lookup bits of the circuit when it is created without a pinning, if it has a lookup table.
+    fn lookup_bits(&self) -> Option<usize> {
+        None
+    }
+
+    /// Checks the arguments of [`PreCircuit::create_circuit`] for which it would panic, since it cannot return an error:
+    /// the prover needs the break points of a pinning, and otherwise the lookup table must fit in a circuit of degree `k`.
+    fn check_create(
+        &self,
+        stage: CircuitBuilderStage,
+        pinning: Option<&Self::Pinning>,
+        k: u32,
+    ) -> Result<(), ScaffoldError> {
+        match (pinning, self.lookup_bits()) {
+            (Some(_), _) => Ok(()),
+            (None, _) if stage == CircuitBuilderStage::Prover => {
+                Err(ScaffoldError::MissingBreakPoints)
+            }
+            (None, Some(lookup_bits)) if lookup_bits >= k as usize => {
+                Err(ScaffoldError::LookupBitsTooLarge { lookup_bits, degree: k })
+            }
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+pub fn run<T: KeygenInputs>(
+    f: impl FnOnce(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone + Sync,
     cli: Cli,
 ) {
     run_builder(|builder, inp, public| f(builder.main(0), inp, public), cli)
 }
 
-pub fn run_builder<T: DeserializeOwned>(
-    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+pub fn run_builder<T: KeygenInputs>(
+    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone + Sync,
     cli: Cli,
 ) {
-    let name = &cli.name;
-    let input_path = PathBuf::from("data")
-        .join(cli.input_path.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.in"))));
-    let private_inputs: T = serde_json::from_reader(
-        File::open(&input_path)
-            .unwrap_or_else(|e| panic!("Input file not found at {input_path:?}. {e:?}")),
-    )
-    .expect("Input file should be a valid JSON file");
+    if let Some(result) = run_without_inputs(CircuitKind::Range, &cli) {
+        return exit_on_error(result);
+    }
+    if let SnarkCmd::Prove { batch: Some(pattern), jobs } = &cli.command {
+        let config = CircuitConfig::from_cli(&cli);
+        let make_precircuit =
+            |private_inputs| pre_run_builder_on_inputs(f.clone(), private_inputs, config);
+        exit_on_error(batch::run_batch_cli(make_precircuit, &cli, pattern, *jobs));
+        return;
+    }
+    let private_inputs = exit_on_error(read_inputs_or_keygen_inputs(&cli));
     run_builder_on_inputs(f, cli, private_inputs)
 }
 
@@ -81,29 +185,26 @@ pub fn run_builder_on_inputs<T: DeserializeOwned>(
     cli: Cli,
     private_inputs: T,
 ) {
-    let precircuit = pre_run_builder_on_inputs(f, private_inputs);
+    let precircuit = pre_run_builder_on_inputs(f, private_inputs, CircuitConfig::from_cli(&cli));
     run_cli(precircuit, cli);
 }
 
 pub fn pre_run_builder_on_inputs<T>(
     f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
     private_inputs: T,
+    config: CircuitConfig,
 ) -> CircuitScaffold<T, impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>)> {
-    CircuitScaffold { f, private_inputs }
+    CircuitScaffold { witness: Witness::Pending { f, private_inputs }, config }
 }
 
+pub use rlc::*;
+
 pub use eth::*;
 mod eth {
-    use std::{
-        cell::RefCell,
-        env::{set_var, var},
-        fs::File,
-        marker::PhantomData,
-        path::PathBuf,
-    };
+    use std::{cell::RefCell, marker::PhantomData};
 
     use axiom_eth::{
-        keccak::{FixedLenRLCs, KeccakChip, VarLenRLCs},
+        keccak::{FixedLenRLCs, FnSynthesize, KeccakChip, VarLenRLCs},
         rlp::{builder::RlcThreadBuilder, RlpChip},
         util::{
             circuit::{PinnableCircuit, PreCircuit},
@@ -122,11 +223,27 @@ mod eth {
     };
     use serde::de::DeserializeOwned;
 
-    use super::{cmd::Cli, run_cli};
+    use super::{
+        batch,
+        cmd::{Cli, SnarkCmd},
+        exit_on_error, read_inputs_or_keygen_inputs, run_cli, run_without_inputs,
+        stats::{self, BuilderStats, CircuitStats},
+        CircuitConfig, CircuitKind, KeygenInputs, ScaffoldError, ScaffoldPreCircuit,
+    };
+
+    /// Default number of rows reserved for blinding factors, which is larger than for `run` because of the keccak columns.
+    const ETH_MINIMUM_ROWS: usize = 109;
+
+    /// `EthCircuitBuilder::configure` does not depend on the phase 1 callback, so any callback type can be used to read a verifying key.
+    pub(crate) type EthVerifierCircuit = EthCircuitBuilder<
+        Fr,
+        fn(&mut RlcThreadBuilder<Fr>, RlpChip<Fr>, (FixedLenRLCs<Fr>, VarLenRLCs<Fr>)),
+    >;
 
     pub struct EthScaffold<T, FN, F1> {
         f: FN,
         private_inputs: T,
+        config: CircuitConfig,
         _f1: PhantomData<F1>,
     }
 
@@ -149,10 +266,34 @@ mod eth {
             pinning: Option<Self::Pinning>,
             params: &ParamsKZG<Bn256>,
         ) -> impl PinnableCircuit<Fr> {
+            self.create(stage, pinning, params)
+        }
+    }
+
+    impl<T, FN, F1> EthScaffold<T, FN, F1>
+    where
+        FN: FnOnce(
+            &mut GateThreadBuilder<Fr>,
+            &EthChip<Fr>,
+            &mut KeccakChip<Fr>,
+            T,
+            &mut Vec<AssignedValue<Fr>>,
+        ) -> F1,
+        F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &EthChip<Fr>) + Clone,
+    {
+        fn create(
+            self,
+            stage: CircuitBuilderStage,
+            pinning: Option<EthConfigPinning>,
+            params: &ParamsKZG<Bn256>,
+        ) -> EthCircuitBuilder<Fr, impl FnSynthesize<Fr>> {
             let mut builder = RlcThreadBuilder::new(stage == CircuitBuilderStage::Prover);
-            let lookup_bits: usize =
-                var("LOOKUP_BITS").unwrap_or_else(|_| ETH_LOOKUP_BITS.to_string()).parse().unwrap();
-            set_var("LOOKUP_BITS", lookup_bits.to_string());
+            let lookup_bits = match &pinning {
+                Some(pinning) => pinning.params.lookup_bits,
+                None => self.config.lookup_bits,
+            }
+            .unwrap_or(ETH_LOOKUP_BITS);
+            // the lookup bits reach `EthCircuitBuilder::configure` through `EthConfigParams`, which `config` computes from `range` and the pinning records
             let range = RangeChip::default(lookup_bits);
             let chip = EthChip::new(RlpChip::new(&range, None), None);
             let mut keccak = KeccakChip::default();
@@ -184,23 +325,79 @@ mod eth {
                 },
             );
             if stage != CircuitBuilderStage::Prover {
-                circuit.config(params.k() as usize, Some(109));
+                let minimum_rows = self.config.minimum_rows.unwrap_or(ETH_MINIMUM_ROWS);
+                circuit.config(params.k() as usize, Some(minimum_rows));
             }
             circuit
         }
     }
 
-    pub fn run_eth<T, FN, F1>(f: FN, cli: Cli)
+    impl<T, FN, F1> ScaffoldPreCircuit for EthScaffold<T, FN, F1>
     where
-        T: DeserializeOwned,
         FN: FnOnce(
-            &mut Context<Fr>,
+            &mut GateThreadBuilder<Fr>,
             &EthChip<Fr>,
             &mut KeccakChip<Fr>,
             T,
             &mut Vec<AssignedValue<Fr>>,
         ) -> F1,
         F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &EthChip<Fr>) + Clone,
+    {
+        const KIND: CircuitKind = CircuitKind::Eth;
+
+        fn lookup_bits(&self) -> Option<usize> {
+            Some(self.config.lookup_bits.unwrap_or(ETH_LOOKUP_BITS))
+        }
+
+        fn stats(self, params: &ParamsKZG<Bn256>) -> Result<CircuitStats, ScaffoldError> {
+            let k = params.k();
+            let minimum_rows = self.config.minimum_rows.unwrap_or(ETH_MINIMUM_ROWS);
+            let circuit = self.create(CircuitBuilderStage::Mock, None, params);
+            let eth_params = circuit.config(k as usize, Some(minimum_rows));
+            stats::circuit_stats(&circuit, k, |circuit| {
+                let builder = circuit.circuit.builder.borrow();
+                let mut stats = BuilderStats::from_builder(&builder.gate_builder);
+                // RLC cells are assigned in phase 1
+                stats.advice_cells_per_phase[1] +=
+                    builder.threads_rlc.iter().map(|ctx| ctx.advice.len()).sum::<usize>();
+                stats.lookup_advice_columns_per_phase = eth_params.num_lookup_advice.clone();
+                let keccak = circuit.keccak.borrow();
+                stats.keccak_rows = Some(keccak_rows(&keccak, eth_params.keccak_rows_per_round));
+                stats
+            })
+        }
+    }
+
+    /// Number of bytes absorbed by each keccak-f permutation
+    const KECCAK_RATE_BYTES: usize = 136;
+    /// Number of rounds of the keccak circuit per keccak-f permutation, which is `NUM_ROUNDS + 1`
+    const KECCAK_ROUNDS_PER_F: usize = 25;
+
+    /// Rows used by the keccak circuit for all the keccak queries of `keccak`, which include one dummy permutation at the start.
+    fn keccak_rows(keccak: &KeccakChip<Fr>, rows_per_round: usize) -> usize {
+        let num_keccak_f: usize = keccak
+            .fixed_len_queries
+            .iter()
+            .map(|query| query.input_assigned.len())
+            .chain(keccak.var_len_queries.iter().map(|query| query.max_bytes))
+            .map(|num_bytes| num_bytes / KECCAK_RATE_BYTES + 1)
+            .sum();
+        (num_keccak_f + 1) * KECCAK_ROUNDS_PER_F * rows_per_round
+    }
+
+    pub fn run_eth<T, FN, F1>(f: FN, cli: Cli)
+    where
+        T: KeygenInputs,
+        FN: FnOnce(
+                &mut Context<Fr>,
+                &EthChip<Fr>,
+                &mut KeccakChip<Fr>,
+                T,
+                &mut Vec<AssignedValue<Fr>>,
+            ) -> F1
+            + Clone
+            + Sync,
+        F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &EthChip<Fr>) + Clone,
     {
         run_eth_builder(
             |builder, chip, keccak, inp, public| f(builder.main(0), chip, keccak, inp, public),
@@ -210,24 +407,38 @@ mod eth {
 
     pub fn run_eth_builder<T, FN, F1>(f: FN, cli: Cli)
     where
-        T: DeserializeOwned,
+        T: KeygenInputs,
         FN: FnOnce(
-            &mut GateThreadBuilder<Fr>,
-            &EthChip<Fr>,
-            &mut KeccakChip<Fr>,
-            T,
-            &mut Vec<AssignedValue<Fr>>,
-        ) -> F1,
+                &mut GateThreadBuilder<Fr>,
+                &EthChip<Fr>,
+                &mut KeccakChip<Fr>,
+                T,
+                &mut Vec<AssignedValue<Fr>>,
+            ) -> F1
+            + Clone
+            + Sync,
         F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &EthChip<Fr>) + Clone,
     {
-        let name = &cli.name;
-        let input_path = PathBuf::from("data")
-            .join(cli.input_path.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.in"))));
-        let private_inputs: T = serde_json::from_reader(
-            File::open(&input_path)
-                .unwrap_or_else(|e| panic!("Input file not found at {input_path:?}. {e:?}")),
-        )
-        .expect("Input file should be a valid JSON file");
+        if let Some(result) = run_without_inputs(CircuitKind::Eth, &cli) {
+            return exit_on_error(result);
+        }
+        if let SnarkCmd::Prove { batch: Some(pattern), jobs } = &cli.command {
+            // axiom-eth configures `EthCircuitBuilder` through process environment variables, which it also sets while configuring,
+            // and the prover configures the circuit again for each proof, so proofs cannot run on several threads
+            if *jobs > 1 {
+                return exit_on_error(Err(ScaffoldError::ParallelBatchUnsupported));
+            }
+            let config = CircuitConfig::from_cli(&cli);
+            let make_precircuit = |private_inputs| EthScaffold {
+                f: f.clone(),
+                private_inputs,
+                config,
+                _f1: PhantomData,
+            };
+            exit_on_error(batch::run_batch_cli(make_precircuit, &cli, pattern, *jobs));
+            return;
+        }
+        let private_inputs = exit_on_error(read_inputs_or_keygen_inputs(&cli));
         run_eth_builder_on_inputs(f, cli, private_inputs)
     }
 
@@ -243,156 +454,535 @@ mod eth {
         ) -> F1,
         F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &EthChip<Fr>) + Clone,
     {
-        let precircuit = EthScaffold { f, private_inputs, _f1: PhantomData };
+        let config = CircuitConfig::from_cli(&cli);
+        let precircuit = EthScaffold { f, private_inputs, config, _f1: PhantomData };
         run_cli(precircuit, cli);
     }
 }
 
-pub fn run_cli<P: PreCircuit>(precircuit: P, cli: Cli) {
-    let name = cli.name;
-    let k = cli.degree;
+/// Locations of the files read and written by the scaffold for the circuit named `name`.
+#[derive(Clone, Debug)]
+pub struct CircuitPaths {
+    pub name: String,
+    pub config_path: PathBuf,
+    pub data_path: PathBuf,
+}
+
+impl CircuitPaths {
+    pub fn new(name: impl Into<String>, config_path: PathBuf, data_path: PathBuf) -> Self {
+        Self { name: name.into(), config_path, data_path }
+    }
+
+    pub fn from_cli(cli: &Cli) -> Self {
+        Self::new(
+            cli.name.clone(),
+            cli.config_path.clone().unwrap_or_else(|| PathBuf::from("configs")),
+            cli.data_path.clone().unwrap_or_else(|| PathBuf::from("data")),
+        )
+    }
+
+    pub fn pinning(&self) -> PathBuf {
+        self.config_path.join(format!("{}.json", self.name))
+    }
+
+    pub fn pk(&self) -> PathBuf {
+        self.data(format!("{}.pk", self.name))
+    }
+
+    pub fn vk(&self) -> PathBuf {
+        self.data(format!("{}.vk", self.name))
+    }
+
+    pub fn snark(&self) -> PathBuf {
+        self.data(format!("{}.snark", self.name))
+    }
+
+    pub fn instances(&self) -> PathBuf {
+        self.data(format!("{}.instances.json", self.name))
+    }
+
+    pub fn yul(&self) -> PathBuf {
+        self.data(format!("{}.yul", self.name))
+    }
+
+    pub fn calldata(&self) -> PathBuf {
+        self.data(format!("{}.calldata", self.name))
+    }
+
+    /// The aggregation circuit has its own keys, pinning, and snark, stored under the name `{name}_agg`.
+    pub fn aggregation(&self) -> Self {
+        Self { name: format!("{}_agg", self.name), ..self.clone() }
+    }
+
+    /// Snark of a batch input, named after the input file, e.g. `{name}.query1.snark` for `query1.in`.
+    pub fn batch_snark(&self, input: &Path) -> PathBuf {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        self.data(format!("{}.{stem}.snark", self.name))
+    }
+
+    pub fn batch_instances(&self, input: &Path) -> PathBuf {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        self.data(format!("{}.{stem}.instances.json", self.name))
+    }
+
+    pub fn batch_manifest(&self) -> PathBuf {
+        self.data(format!("{}.batch.json", self.name))
+    }
+
+    fn data(&self, file_name: String) -> PathBuf {
+        self.data_path.join(file_name)
+    }
+
+    /// Creates the config and data directories if they do not exist yet.
+    pub fn create_dirs(&self) -> Result<(), ScaffoldError> {
+        for dir in [&self.config_path, &self.data_path] {
+            fs::create_dir_all(dir).map_err(ScaffoldError::io(dir))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the private inputs of the circuit as JSON from `data/{input_path}`, where `input_path` defaults to `{name}.in`.
+pub fn read_inputs<T: DeserializeOwned>(cli: &Cli) -> Result<T, ScaffoldError> {
+    let name = &cli.name;
+    let input_path = PathBuf::from("data")
+        .join(cli.input_path.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.in"))));
+    let f = File::open(&input_path)
+        .map_err(|source| ScaffoldError::MissingInput { path: input_path.clone(), source })?;
+    serde_json::from_reader(BufReader::new(f))
+        .map_err(|source| ScaffoldError::InvalidInput { path: input_path, source })
+}
+
+/// Same as [`read_inputs`], except that during keygen and stats, [`KeygenInputs::keygen_inputs`] is used if no input file was specified and `data/{name}.in` does not exist.
+pub fn read_inputs_or_keygen_inputs<T: KeygenInputs>(cli: &Cli) -> Result<T, ScaffoldError> {
+    match read_inputs(cli) {
+        Err(ScaffoldError::MissingInput { path, .. })
+            if matches!(cli.command, SnarkCmd::Keygen | SnarkCmd::Stats { .. })
+                && cli.input_path.is_none() =>
+        {
+            println!("No input file found at {path:?}, generating keys with placeholder inputs");
+            Ok(T::keygen_inputs())
+        }
+        result => result,
+    }
+}
+
+/// Exits the process with the error message instead of panicking, for use in command line binaries.
+pub fn exit_on_error<T>(result: Result<T, ScaffoldError>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(e.exit_code())
+    })
+}
 
-    let config_path = cli.config_path.unwrap_or_else(|| PathBuf::from("configs"));
-    let data_path = cli.data_path.unwrap_or_else(|| PathBuf::from("data"));
-    fs::create_dir_all(&config_path).unwrap();
-    fs::create_dir_all(&data_path).unwrap();
+pub fn run_cli<P: ScaffoldPreCircuit>(precircuit: P, cli: Cli)
+where
+    P::Pinning: DeserializeOwned,
+{
+    exit_on_error(try_run_cli(precircuit, cli))
+}
 
-    let params = gen_srs(k);
-    println!("Universal trusted setup (unsafe!) available at: params/kzg_bn254_{k}.srs");
-    match cli.command {
+/// Same as [`run_cli`], but returns an error instead of exiting the process.
+pub fn try_run_cli<P: ScaffoldPreCircuit>(precircuit: P, cli: Cli) -> Result<(), ScaffoldError>
+where
+    P::Pinning: DeserializeOwned,
+{
+    if let SnarkCmd::Aggregate { step, snarks } = &cli.command {
+        return run_aggregation_cli(*step, snarks, &cli);
+    }
+    let paths = CircuitPaths::from_cli(&cli);
+    paths.create_dirs()?;
+
+    let (precircuit, k) = match (cli.degree, &cli.command) {
+        (Degree::Fixed(k), _) => (precircuit, k),
+        (Degree::Auto, SnarkCmd::Mock | SnarkCmd::Keygen | SnarkCmd::Stats { .. }) => {
+            let (precircuit, k) = precircuit.auto_degree()?;
+            println!("Automatically chose degree k = {k}");
+            (precircuit, k)
+        }
+        (Degree::Auto, _) => (precircuit, read_pinning::<P::Pinning>(&paths)?.degree()),
+    };
+    let params = load_params(cli.params_path.as_deref(), k)?;
+    match &cli.command {
         SnarkCmd::Mock => {
-            let circuit = precircuit.create_circuit(CircuitBuilderStage::Mock, None, &params);
-            MockProver::run(k, &circuit, circuit.instances()).unwrap().assert_satisfied();
+            let result = mock(precircuit, &params);
+            if let (Err(ScaffoldError::Unsatisfied(report)), Some(path)) =
+                (&result, &cli.mock_report)
+            {
+                report.write_json(path)?;
+                eprintln!("Mock prover report written to: {path:?}");
+            }
+            result?;
+            println!("Mock prover: all constraints satisfied!");
         }
         SnarkCmd::Keygen => {
-            let pk_path = data_path.join(PathBuf::from(format!("{name}.pk")));
-            if pk_path.exists() {
-                fs::remove_file(&pk_path).unwrap();
+            keygen(precircuit, &params, &paths, cli.create_contract)?;
+            println!("Proving key written to: {:?}", paths.pk());
+            println!("Verifying key written to: {:?}", paths.vk());
+            if cli.create_contract {
+                println!("Yul verifier contract written to: {:?}", paths.yul());
             }
-            let pinning_path = config_path.join(PathBuf::from(format!("{name}.json")));
-            let pk = precircuit.create_pk(&params, &pk_path, pinning_path);
-            println!("Proving key written to: {pk_path:?}");
-
-            let vk_path = data_path.join(PathBuf::from(format!("{name}.vk")));
-            let f = File::create(&vk_path).unwrap();
-            let mut writer = BufWriter::new(f);
-            pk.get_vk()
-                .write(&mut writer, SerdeFormat::RawBytes)
-                .expect("writing vkey should not fail");
-            println!("Verifying key written to: {vk_path:?}");
         }
-        SnarkCmd::Prove => {
-            let pinning_path = config_path.join(PathBuf::from(format!("{name}.json")));
-            let pinning = P::Pinning::from_path(pinning_path);
-            pinning.set_var();
-            let circuit =
-                precircuit.create_circuit(CircuitBuilderStage::Prover, Some(pinning), &params);
-            let pk_path = data_path.join(PathBuf::from(format!("{name}.pk")));
-            let pk = custom_read_pk(pk_path, &circuit);
-            let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
-            if snark_path.exists() {
-                fs::remove_file(&snark_path).unwrap();
-            }
-            gen_snark_shplonk(&params, &pk, circuit, Some(&snark_path));
-            println!("Snark written to: {snark_path:?}");
+        SnarkCmd::Prove { batch: Some(_), .. } => return Err(ScaffoldError::BatchUnsupported),
+        SnarkCmd::Prove { .. } if cli.create_contract => {
+            prove_evm(precircuit, &params, &paths)?;
+            println!("EVM calldata written to: {:?}", paths.calldata());
+            println!("EVM verifier contract accepted the proof!");
+        }
+        SnarkCmd::Prove { .. } => {
+            prove(precircuit, &params, &paths, ProofOptions::from_cli(&cli))?;
+            println!("Snark written to: {:?}", paths.snark());
+            println!("Public instances written to: {:?}", paths.instances());
         }
-        SnarkCmd::Verify => {
-            let vk_path = data_path.join(PathBuf::from(format!("{name}.vk")));
-            let circuit = precircuit.create_circuit(CircuitBuilderStage::Keygen, None, &params);
-            let vk = custom_read_vk(vk_path, &circuit);
-            let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
-            let snark = read_snark(&snark_path)
-                .unwrap_or_else(|e| panic!("Snark not found at {snark_path:?}. {e:?}"));
-
-            let verifier_params = params.verifier_params();
-            let strategy = SingleStrategy::new(&params);
-            let mut transcript =
-                PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
-            let instance = &snark.instances[0][..];
-            verify_proof::<
-                KZGCommitmentScheme<Bn256>,
-                VerifierSHPLONK<'_, Bn256>,
-                _,
-                _,
-                SingleStrategy<'_, Bn256>,
-            >(verifier_params, &vk, strategy, &[&[instance]], &mut transcript)
-            .unwrap();
+        SnarkCmd::Verify { expect_instances } => {
+            let snark = verify(P::KIND, &params, &paths)?;
+            check_expected_instances(&snark, expect_instances.as_deref())?;
             println!("Snark verified successfully!");
         }
+        SnarkCmd::Stats { json } => {
+            precircuit.check_create(CircuitBuilderStage::Mock, None, k)?;
+            let stats = precircuit.stats(&params)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+            } else {
+                println!("{stats}");
+            }
+        }
+        SnarkCmd::Aggregate { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Runs the commands that need neither the circuit nor its private inputs. Returns `None` for all other commands.
+fn run_without_inputs(kind: CircuitKind, cli: &Cli) -> Option<Result<(), ScaffoldError>> {
+    match &cli.command {
+        SnarkCmd::Verify { expect_instances } => {
+            Some(run_verify(kind, cli, expect_instances.as_deref()))
+        }
+        SnarkCmd::Aggregate { step, snarks } => Some(run_aggregation_cli(*step, snarks, cli)),
+        _ => None,
+    }
+}
+
+fn run_verify(
+    kind: CircuitKind,
+    cli: &Cli,
+    expect_instances: Option<&Path>,
+) -> Result<(), ScaffoldError> {
+    let paths = CircuitPaths::from_cli(cli);
+    let k = match cli.degree {
+        Degree::Fixed(k) => k,
+        Degree::Auto => verifier::load_pinning(kind, &paths)?,
+    };
+    let params = load_params(cli.params_path.as_deref(), k)?;
+    let snark = verify(kind, &params, &paths)?;
+    check_expected_instances(&snark, expect_instances)?;
+    println!("Snark verified successfully!");
+    Ok(())
+}
+
+/// Checks the public instances of a verified snark against the file passed to `verify --expect-instances`, if any.
+pub fn check_expected_instances(snark: &Snark, path: Option<&Path>) -> Result<(), ScaffoldError> {
+    if let Some(path) = path {
+        instances::check_instances(path, &snark.instances)?;
+        println!("Public instances match {path:?}");
+    }
+    Ok(())
+}
+
+/// Reads the trusted setup from `params_path` if specified, downsized to degree `k`. Otherwise generates an unsafe setup for testing.
+pub fn load_params(params_path: Option<&Path>, k: u32) -> Result<ParamsKZG<Bn256>, ScaffoldError> {
+    match params_path {
+        Some(path) => {
+            let params = srs::read_params(path, k)?;
+            println!("Universal trusted setup read from: {path:?}");
+            Ok(params)
+        }
+        None => {
+            let params = gen_srs(k);
+            println!("Universal trusted setup (unsafe!) available at: params/kzg_bn254_{k}.srs");
+            Ok(params)
+        }
+    }
+}
+
+/// Runs a step of the aggregation circuit without needing the circuit that produced the snarks, nor its inputs.
+fn run_aggregation_cli(
+    step: AggregateCmd,
+    snarks: &[PathBuf],
+    cli: &Cli,
+) -> Result<(), ScaffoldError> {
+    CircuitPaths::from_cli(cli).create_dirs()?;
+    let k = match (cli.degree, step) {
+        (Degree::Fixed(k), _) => k,
+        (Degree::Auto, AggregateCmd::Prove | AggregateCmd::Verify) => {
+            read_pinning::<AggregationConfigPinning>(&AggregationScaffold::paths(cli))?.degree()
+        }
+        (Degree::Auto, AggregateCmd::Mock | AggregateCmd::Keygen) => {
+            return Err(ScaffoldError::AutoDegreeUnsupported)
+        }
+    };
+    let params = load_params(cli.params_path.as_deref(), k)?;
+    run_aggregation(step, snarks, cli, &params)
+}
+
+/// Runs a step of the aggregation circuit on the snarks in the data directory.
+fn run_aggregation(
+    step: AggregateCmd,
+    snarks: &[PathBuf],
+    cli: &Cli,
+    params: &ParamsKZG<Bn256>,
+) -> Result<(), ScaffoldError> {
+    let paths = AggregationScaffold::paths(cli);
+    match step {
+        AggregateCmd::Mock => {
+            let precircuit = AggregationScaffold::from_cli(cli, snarks)?;
+            mock(precircuit, params)?;
+            println!("Mock prover: all constraints satisfied!");
+        }
+        AggregateCmd::Keygen => {
+            let precircuit = AggregationScaffold::from_cli(cli, snarks)?;
+            keygen(precircuit, params, &paths, cli.create_contract)?;
+            println!("Proving key written to: {:?}", paths.pk());
+            println!("Verifying key written to: {:?}", paths.vk());
+            if cli.create_contract {
+                println!("Yul verifier contract written to: {:?}", paths.yul());
+            }
+        }
+        AggregateCmd::Prove => {
+            let precircuit = AggregationScaffold::from_cli(cli, snarks)?;
+            prove(precircuit, params, &paths, ProofOptions::from_cli(cli))?;
+            println!("Aggregation snark written to: {:?}", paths.snark());
+            println!("Public instances written to: {:?}", paths.instances());
+        }
+        AggregateCmd::Verify => {
+            verify(CircuitKind::Aggregation, params, &paths)?;
+            println!("Aggregation snark verified successfully!");
+        }
+    }
+    Ok(())
+}
+
+/// Runs the mock prover on the circuit, returning a report of all unsatisfied constraints as an error.
+pub fn mock<P: ScaffoldPreCircuit>(
+    precircuit: P,
+    params: &ParamsKZG<Bn256>,
+) -> Result<(), ScaffoldError> {
+    precircuit.check_create(CircuitBuilderStage::Mock, None, params.k())?;
+    let circuit = precircuit.create_circuit(CircuitBuilderStage::Mock, None, params);
+    let instances = circuit.instances();
+    MockProver::run(params.k(), &circuit, instances.clone())?
+        .verify()
+        .map_err(|failures| ScaffoldError::Unsatisfied(MockReport::new(failures, &instances)))
+}
+
+/// Generates the proving and verifying keys of the circuit and writes them, together with the circuit pinning, to `paths`.
+/// If `create_contract` is true, also writes the Yul code of a verifier contract.
+pub fn keygen<P: ScaffoldPreCircuit>(
+    precircuit: P,
+    params: &ParamsKZG<Bn256>,
+    paths: &CircuitPaths,
+    create_contract: bool,
+) -> Result<ProvingKey<G1Affine>, ScaffoldError> {
+    precircuit.check_create(CircuitBuilderStage::Keygen, None, params.k())?;
+    let circuit = precircuit.create_circuit(CircuitBuilderStage::Keygen, None, params);
+    let vk = keygen_vk(params, &circuit)?;
+    let pk = keygen_pk(params, vk, &circuit)?;
+    circuit.write_pinning(paths.pinning());
+
+    let pk_path = paths.pk();
+    let mut writer = BufWriter::new(File::create(&pk_path).map_err(ScaffoldError::io(&pk_path))?);
+    pk.write(&mut writer, SerdeFormat::RawBytesUnchecked).map_err(ScaffoldError::io(&pk_path))?;
+
+    let vk_path = paths.vk();
+    let mut writer = BufWriter::new(File::create(&vk_path).map_err(ScaffoldError::io(&vk_path))?);
+    pk.get_vk().write(&mut writer, SerdeFormat::RawBytes).map_err(ScaffoldError::io(&vk_path))?;
+
+    if create_contract {
+        let yul_path = paths.yul();
+        custom_gen_evm_verifier_shplonk(params, pk.get_vk(), &circuit, Some(yul_path.as_path()));
     }
+    Ok(pk)
 }
 
-fn custom_read_pk<C, P>(fname: P, _: &C) -> ProvingKey<G1Affine>
+/// Generates a snark for the circuit using the proving key and pinning created by [`keygen`], and writes it and its public instances to `paths`.
+/// The proof is created with the transcript and multi-open scheme of `options`, which are recorded next to the snark.
+pub fn prove<P: PreCircuit>(
+    precircuit: P,
+    params: &ParamsKZG<Bn256>,
+    paths: &CircuitPaths,
+    options: ProofOptions,
+) -> Result<Snark, ScaffoldError>
 where
-    C: Circuit<Fr>,
-    P: AsRef<Path>,
+    P::Pinning: DeserializeOwned,
 {
-    read_pk::<C>(fname.as_ref())
-        .unwrap_or_else(|e| panic!("Failed to open file: {:?}: {e:?}", fname.as_ref()))
+    let pinning = read_pinning::<P::Pinning>(paths)?;
+    pinning.set_var();
+    let circuit = precircuit.create_circuit(CircuitBuilderStage::Prover, Some(pinning), params);
+    let pk = custom_read_pk(paths.pk(), &circuit)?;
+    let snark = gen_snark(params, &pk, circuit, options)?;
+    write_snark(paths.snark(), &snark)?;
+    options.write_for(&paths.snark())?;
+    instances::write_instances(&paths.instances(), &snark.instances)?;
+    Ok(snark)
 }
 
-fn custom_read_vk<C, P>(fname: P, _: &C) -> VerifyingKey<G1Affine>
+/// Creates a proof for the EVM verifier contract, which uses a Keccak transcript, writes it together with the public instances as calldata to `paths`, writes the public instances as JSON,
+/// and checks that the verifier contract accepts it in a local EVM. Returns the calldata as a hex string.
+pub fn prove_evm<P: PreCircuit>(
+    precircuit: P,
+    params: &ParamsKZG<Bn256>,
+    paths: &CircuitPaths,
+) -> Result<String, ScaffoldError>
+where
+    P::Pinning: DeserializeOwned,
+{
+    let pinning = read_pinning::<P::Pinning>(paths)?;
+    pinning.set_var();
+    let circuit = precircuit.create_circuit(CircuitBuilderStage::Prover, Some(pinning), params);
+    let pk = custom_read_pk(paths.pk(), &circuit)?;
+
+    let instances = circuit.instances();
+    let deployment_code = custom_gen_evm_verifier_shplonk(params, pk.get_vk(), &circuit, None);
+    let proof = gen_evm_proof_shplonk(params, &pk, circuit, instances.clone());
+    let calldata_path = paths.calldata();
+    let calldata = write_calldata(&instances, &proof, &calldata_path)
+        .map_err(ScaffoldError::io(&calldata_path))?;
+    instances::write_instances(&paths.instances(), &instances)?;
+
+    // `evm_verify` panics if the verifier contract reverts
+    panic::catch_unwind(|| evm_verify(deployment_code, instances, proof))
+        .map_err(|_| ScaffoldError::EvmVerificationFailed)?;
+    Ok(calldata)
+}
+
+/// Reads the circuit pinning written by [`keygen`].
+pub(crate) fn read_pinning<Pinning: Halo2ConfigPinning + DeserializeOwned>(
+    paths: &CircuitPaths,
+) -> Result<Pinning, ScaffoldError> {
+    config::read_pinning(&paths.pinning())
+}
+
+/// Creates a proof with the transcript and multi-open scheme of `options`. With the default Poseidon transcript and SHPLONK, the snark can be verified natively or inside another circuit.
+fn gen_snark<C: CircuitExt<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    options: ProofOptions,
+) -> Result<Snark, ScaffoldError> {
+    let protocol = compile(
+        params,
+        pk.get_vk(),
+        Config::kzg()
+            .with_num_instance(circuit.num_instance())
+            .with_accumulator_indices(C::accumulator_indices()),
+    );
+    let instances = circuit.instances();
+    let proof = proof::create_proof_with(params, pk, circuit, &instances, options)?;
+    Ok(Snark::new(protocol, instances, proof))
+}
+
+/// Writes the snark in the same format as `snark_verifier_sdk`, so that it can be read back with `read_snark`.
+pub fn write_snark(path: impl AsRef<Path>, snark: &Snark) -> Result<(), ScaffoldError> {
+    let path = path.as_ref();
+    let writer = BufWriter::new(File::create(path).map_err(ScaffoldError::io(path))?);
+    bincode::serialize_into(writer, snark)
+        .map_err(|e| ScaffoldError::io(path)(io::Error::new(io::ErrorKind::Other, e)))
+}
+
+fn custom_read_pk<C, P>(fname: P, _: &C) -> Result<ProvingKey<G1Affine>, ScaffoldError>
 where
     C: Circuit<Fr>,
     P: AsRef<Path>,
 {
-    let f = File::open(&fname)
-        .unwrap_or_else(|e| panic!("Failed to open file: {:?}: {e:?}", fname.as_ref()));
+    let path = fname.as_ref().to_path_buf();
+    let f = File::open(&path)
+        .map_err(|source| ScaffoldError::MissingKey { path: path.clone(), source })?;
     let mut bufreader = BufReader::new(f);
-    VerifyingKey::read::<_, C>(&mut bufreader, SerdeFormat::RawBytes).expect("Could not read vkey")
+    ProvingKey::read::<_, C>(&mut bufreader, SerdeFormat::RawBytesUnchecked)
+        .map_err(|source| ScaffoldError::KeyMismatch { path, source })
+}
+
+/// Generates the Yul code of a SHPLONK verifier contract for `vk`, writing it to `path` if specified, and returns the compiled deployment bytecode.
+/// Compiling the Yul code requires `solc` to be installed.
+fn custom_gen_evm_verifier_shplonk<C: CircuitExt<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    circuit: &C,
+    path: Option<&Path>,
+) -> Vec<u8> {
+    gen_evm_verifier_shplonk::<C>(params, vk, circuit.num_instance(), path)
 }
 
 impl<T, Fn> PreCircuit for CircuitScaffold<T, Fn>
 where
     Fn: FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
 {
-    type Pinning = AggregationConfigPinning;
+    type Pinning = ScaffoldConfigPinning;
 
-    /// Creates a Halo2 circuit from the given function.
     fn create_circuit(
         self,
         stage: CircuitBuilderStage,
         pinning: Option<Self::Pinning>,
         params: &ParamsKZG<Bn256>,
     ) -> impl PinnableCircuit<Fr> {
-        // we initiate a "thread builder". This is what keeps track of the execution trace of our program. If not in proving mode, it also keeps track of the ZK constraints.
-        let mut builder = match stage {
-            CircuitBuilderStage::Prover => GateThreadBuilder::new(true),
-            _ => GateThreadBuilder::new(false),
+        self.create(stage, pinning, params.k())
+    }
+}
+
+impl<T, Fn> CircuitScaffold<T, Fn>
+where
+    Fn: FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+{
+    /// Creates a Halo2 circuit of degree `k` from the given function.
+    fn create(
+        self,
+        stage: CircuitBuilderStage,
+        pinning: Option<ScaffoldConfigPinning>,
+        k: u32,
+    ) -> ScaffoldCircuitBuilder<Fr> {
+        let k = k as usize;
+        // when proving, the configuration is read from the pinning; otherwise the number of columns is auto-tuned below
+        let (config_params, break_points) = match pinning {
+            Some(pinning) => (pinning.params, Some(pinning.break_points)),
+            None => {
+                // `lookup_bits` determines whether the circuit has a lookup table, with 2^lookup_bits rows. If not specified, the circuit does not use range checks
+                let lookup_bits = self.config.lookup_bits.unwrap_or(0);
+                // we use a lookup table with 2^lookup_bits rows. Due to blinding factors, we need a little more than 2^lookup_bits rows total in our circuit
+                // `check_create` returns an error before this is reached through the scaffold
+                assert!(lookup_bits < k, "lookup bits needs to be less than DEGREE");
+                // minimum rows is the number of rows used for blinding factors. This depends on the circuit itself, but we can guess the number and change it if something breaks (default 9 usually works)
+                let minimum_rows = self.config.minimum_rows.unwrap_or(DEFAULT_MINIMUM_ROWS);
+                let config_params = ScaffoldConfigParams {
+                    degree: k as u32,
+                    lookup_bits,
+                    minimum_rows,
+                    ..Default::default()
+                };
+                (config_params, None)
+            }
         };
-        let k = params.k() as usize;
-        // we use env var `LOOKUP_BITS` to determine whether to use `GateThreadBuilder` or `RangeCircuitBuilder`. The difference is that the latter creates a lookup table with 2^LOOKUP_BITS rows, while the former does not.
-        let lookup_bits: usize = var("LOOKUP_BITS")
-            .map(|str| {
-                let lookup_bits = str.parse().unwrap();
-                // we use a lookup table with 2^LOOKUP_BITS rows. Due to blinding factors, we need a little more than 2^LOOKUP_BITS rows total in our circuit
-                assert!(lookup_bits < k, "LOOKUP_BITS needs to be less than DEGREE");
-                lookup_bits
-            })
-            .unwrap_or(0);
-        // set `LOOKUP_BITS` to '0' if it is not set; this is just a technicality to re-use some other code
-        set_var("LOOKUP_BITS", lookup_bits.to_string());
-        // builder.main(phase) gets a default "main" thread for the given phase. For most purposes we only need to think about phase 0
-        // we need a 64-bit number as input in this case
-        // while `some_algorithm_in_zk` was written generically for any field `F`, in practice we use the scalar field of the BN254 curve because that's what the proving system backend uses
-        let mut assigned_instances = vec![];
-        (self.f)(&mut builder, self.private_inputs, &mut assigned_instances);
+        // makes `lookup_bits()` available to `f`
+        config::set_current(config_params.clone());
+        let (mut builder, assigned_instances) =
+            self.witness.generate(stage == CircuitBuilderStage::Prover);
 
         // now `builder` contains the execution trace, and we are ready to actually create the circuit
-        // minimum rows is the number of rows used for blinding factors. This depends on the circuit itself, but we can guess the number and change it if something breaks (default 9 usually works)
-        let minimum_rows = var("MINIMUM_ROWS").unwrap_or_else(|_| "9".to_string()).parse().unwrap();
         // auto-tune circuit
         match stage {
             CircuitBuilderStage::Prover => {}
             _ => {
-                builder.config(k, Some(minimum_rows));
+                let minimum_rows = config_params.minimum_rows;
+                let flex_gate_params = builder.config(k, Some(minimum_rows));
+                config::set_current(config_params.with_columns(flex_gate_params));
             }
         };
 
         let circuit = match stage {
+            // `check_create` returns an error before this is reached through the scaffold
             CircuitBuilderStage::Prover => RangeCircuitBuilder::prover(
                 builder,
-                pinning.expect("Circuit pinning not found").break_points(),
+                break_points.expect("Circuit pinning not found"),
             ),
             CircuitBuilderStage::Keygen => RangeCircuitBuilder::keygen(builder),
             CircuitBuilderStage::Mock => RangeCircuitBuilder::mock(builder),
@@ -401,6 +991,43 @@ where
     }
 }
 
+impl<T, Fn> ScaffoldPreCircuit for CircuitScaffold<T, Fn>
+where
+    Fn: FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+{
+    const KIND: CircuitKind = CircuitKind::Range;
+
+    fn lookup_bits(&self) -> Option<usize> {
+        self.config.lookup_bits
+    }
+
+    /// Runs witness generation once and chooses the degree from the number of advice, lookup, and fixed cells.
+    fn auto_degree(self) -> Result<(Self, u32), ScaffoldError> {
+        let lookup_bits = self.config.lookup_bits.unwrap_or(0);
+        let minimum_rows = self.config.minimum_rows.unwrap_or(DEFAULT_MINIMUM_ROWS);
+        // makes `lookup_bits()` available to `f`, before the degree is known
+        config::set_current(ScaffoldConfigParams {
+            lookup_bits,
+            minimum_rows,
+            ..Default::default()
+        });
+        let (builder, assigned_instances) = self.witness.generate(false);
+        let cells = CellCounts::from_builder(&builder, assigned_instances.len());
+        let k = degree::choose_degree(&cells, lookup_bits, minimum_rows)?;
+        let witness = Witness::Generated { builder, assigned_instances };
+        Ok((Self { witness, config: self.config }, k))
+    }
+
+    fn stats(self, params: &ParamsKZG<Bn256>) -> Result<CircuitStats, ScaffoldError> {
+        let circuit = self.create(CircuitBuilderStage::Mock, None, params.k());
+        stats::circuit_stats(&circuit, params.k(), |circuit| {
+            let mut stats = BuilderStats::from_builder(&circuit.0.circuit.0.builder.borrow());
+            stats.lookup_advice_columns_per_phase = config::current().num_lookup_advice_per_phase;
+            stats
+        })
+    }
+}
+
 pub struct ScaffoldCircuitBuilder<F: ScalarField>(RangeWithInstanceCircuitBuilder<F>);
 
 impl<F: ScalarField> Circuit<F> for ScaffoldCircuitBuilder<F> {
@@ -411,8 +1038,21 @@ impl<F: ScalarField> Circuit<F> for ScaffoldCircuitBuilder<F> {
         unimplemented!()
     }
 
+    /// Configures the circuit from the parameters of the current thread, set by `create_circuit` or by loading the pinning.
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-        RangeWithInstanceCircuitBuilder::configure(meta)
+        let params = config::current();
+        let range = RangeConfig::configure(
+            meta,
+            RangeStrategy::Vertical,
+            &params.num_advice_per_phase,
+            &params.num_lookup_advice_per_phase,
+            params.num_fixed,
+            params.lookup_bits,
+            params.degree as usize,
+        );
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        RangeWithInstanceConfig { range, instance }
     }
 
     fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
@@ -431,7 +1071,7 @@ impl<F: ScalarField> CircuitExt<F> for ScaffoldCircuitBuilder<F> {
 }
 
 impl<F: ScalarField> PinnableCircuit<F> for ScaffoldCircuitBuilder<F> {
-    type Pinning = AggregationConfigPinning;
+    type Pinning = ScaffoldConfigPinning;
 
     fn break_points(&self) -> MultiPhaseThreadBreakPoints {
         self.0.circuit.0.break_points.borrow().clone()
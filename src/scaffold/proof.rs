@@ -0,0 +1,233 @@
+//! Choice of the transcript and multi-open scheme used to create a proof, for the `--transcript` and `--multiopen` options.
+//!
+//! Each downstream verifier needs a different combination: a Poseidon transcript with SHPLONK for aggregation inside another circuit,
+//! a Keccak transcript for the EVM verifier, and Blake2b for native verifiers that follow upstream halo2.
+//! The combination is recorded in a metadata file `{snark}.json` next to the snark, from which `verify` chooses the matching verifier.
+//! Snarks without a metadata file are assumed to use the default Poseidon transcript with SHPLONK,
+//! so the metadata file must be sent along with the snark to anyone verifying a proof created with other options.
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, verify_proof, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverGWC, ProverSHPLONK, VerifierGWC, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptRead,
+        TranscriptReadBuffer, TranscriptWrite, TranscriptWriterBuffer,
+    },
+};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use snark_verifier_sdk::{
+    halo2::PoseidonTranscript, snark_verifier::system::halo2::transcript::evm::EvmTranscript,
+    NativeLoader,
+};
+
+use super::{cmd::Cli, ScaffoldError};
+
+/// Hash function used for the Fiat-Shamir transcript.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptKind {
+    /// Cheap to verify inside a circuit, as needed to aggregate the snark
+    #[default]
+    Poseidon,
+    /// The transcript of upstream halo2, for native verifiers
+    Blake2b,
+    /// Cheap to verify in the EVM
+    Keccak,
+}
+
+/// Multi-open argument used to batch the KZG opening proofs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MultiOpenKind {
+    #[default]
+    Shplonk,
+    Gwc,
+}
+
+/// How a proof was created, which the verifier must know to check it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofOptions {
+    pub transcript: TranscriptKind,
+    pub multiopen: MultiOpenKind,
+}
+
+impl fmt::Display for ProofOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let transcript = self.transcript.to_possible_value().unwrap();
+        let multiopen = self.multiopen.to_possible_value().unwrap();
+        write!(f, "{} transcript with {}", transcript.get_name(), multiopen.get_name())
+    }
+}
+
+impl ProofOptions {
+    pub fn from_cli(cli: &Cli) -> Self {
+        Self { transcript: cli.transcript, multiopen: cli.multiopen }
+    }
+
+    /// Location of the metadata of the snark at `snark_path`, e.g. `data/halo2_lib.snark.json`.
+    pub fn path_for(snark_path: &Path) -> PathBuf {
+        let mut path = snark_path.as_os_str().to_owned();
+        path.push(".json");
+        path.into()
+    }
+
+    /// Reads the options recorded next to the snark at `snark_path`, or the defaults if the metadata file does not exist.
+    /// Any other error opening the metadata file is returned rather than falling back to the defaults.
+    pub fn read_for(snark_path: &Path) -> Result<Self, ScaffoldError> {
+        let path = Self::path_for(snark_path);
+        let f = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(ScaffoldError::InvalidSnarkMetadata { path, reason: e.to_string() })
+            }
+        };
+        serde_json::from_reader(BufReader::new(f))
+            .map_err(|e| ScaffoldError::InvalidSnarkMetadata { path, reason: e.to_string() })
+    }
+
+    /// Records the options next to the snark at `snark_path`.
+    pub fn write_for(&self, snark_path: &Path) -> Result<(), ScaffoldError> {
+        let path = Self::path_for(snark_path);
+        let writer = BufWriter::new(File::create(&path).map_err(ScaffoldError::io(&path))?);
+        serde_json::to_writer_pretty(writer, self).map_err(|e| ScaffoldError::io(&path)(e.into()))
+    }
+}
+
+/// Creates a proof for `circuit` with the transcript and multi-open scheme of `options`.
+pub fn create_proof_with<C: Circuit<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instances: &[Vec<Fr>],
+    options: ProofOptions,
+) -> Result<Vec<u8>, ScaffoldError> {
+    let instances = instances.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let proof = match options.transcript {
+        TranscriptKind::Poseidon => {
+            let mut transcript = PoseidonTranscript::<NativeLoader, Vec<u8>>::new::<0>(vec![]);
+            prove_with(params, pk, circuit, &instances, options.multiopen, &mut transcript)?;
+            transcript.finalize()
+        }
+        TranscriptKind::Blake2b => {
+            let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+            prove_with(params, pk, circuit, &instances, options.multiopen, &mut transcript)?;
+            transcript.finalize()
+        }
+        TranscriptKind::Keccak => {
+            let mut transcript = EvmTranscript::<G1Affine, NativeLoader, _, _>::new(vec![]);
+            prove_with(params, pk, circuit, &instances, options.multiopen, &mut transcript)?;
+            transcript.finalize()
+        }
+    };
+    Ok(proof)
+}
+
+fn prove_with<C, E, T>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instances: &[&[Fr]],
+    multiopen: MultiOpenKind,
+    transcript: &mut T,
+) -> Result<(), ScaffoldError>
+where
+    C: Circuit<Fr>,
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptWrite<G1Affine, E>,
+{
+    match multiopen {
+        MultiOpenKind::Shplonk => {
+            create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _, _, _>(
+                params,
+                pk,
+                &[circuit],
+                &[instances],
+                OsRng,
+                transcript,
+            )?
+        }
+        MultiOpenKind::Gwc => create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverGWC<'_, Bn256>,
+            _,
+            _,
+            _,
+            _,
+        >(params, pk, &[circuit], &[instances], OsRng, transcript)?,
+    }
+    Ok(())
+}
+
+/// Verifies a proof created by [`create_proof_with`] with the same `options`.
+pub fn verify_proof_with(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instances: &[Vec<Fr>],
+    options: ProofOptions,
+) -> Result<(), ScaffoldError> {
+    let instances = instances.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    match options.transcript {
+        TranscriptKind::Poseidon => {
+            let mut transcript = PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(proof);
+            verify_with(params, vk, &instances, options.multiopen, &mut transcript)
+        }
+        TranscriptKind::Blake2b => {
+            let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+            verify_with(params, vk, &instances, options.multiopen, &mut transcript)
+        }
+        TranscriptKind::Keccak => {
+            let mut transcript = EvmTranscript::<G1Affine, NativeLoader, _, Vec<u8>>::new(proof);
+            verify_with(params, vk, &instances, options.multiopen, &mut transcript)
+        }
+    }
+}
+
+fn verify_with<E, T>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[&[Fr]],
+    multiopen: MultiOpenKind,
+    transcript: &mut T,
+) -> Result<(), ScaffoldError>
+where
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptRead<G1Affine, E>,
+{
+    let verifier_params = params.verifier_params();
+    let strategy = SingleStrategy::new(params);
+    match multiopen {
+        MultiOpenKind::Shplonk => verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            _,
+            _,
+            SingleStrategy<'_, Bn256>,
+        >(verifier_params, vk, strategy, &[instances], transcript),
+        MultiOpenKind::Gwc => verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierGWC<'_, Bn256>,
+            _,
+            _,
+            SingleStrategy<'_, Bn256>,
+        >(verifier_params, vk, strategy, &[instances], transcript),
+    }
+    .map_err(ScaffoldError::VerificationFailed)
+}
@@ -0,0 +1,203 @@
+//! Readable report of the constraints that the mock prover found unsatisfied.
+//!
+//! Failures are grouped by gate and region, so that a bug that fails the same gate on thousands of rows shows up as one group.
+//! Copy constraint failures on the instance column are linked back to the `AssignedValue` pushed to `make_public` at that index.
+use std::{collections::HashMap, fmt, fs::File, io::BufWriter, path::Path};
+
+use halo2_base::{
+    halo2_proofs::{
+        dev::{metadata, FailureLocation, VerifyFailure},
+        plonk::Any,
+    },
+    utils::ScalarField,
+};
+use serde::Serialize;
+
+use super::ScaffoldError;
+
+/// Number of failures printed per group; the JSON report contains all of them.
+const MAX_PRINTED_PER_GROUP: usize = 5;
+
+#[derive(Debug, Serialize)]
+pub struct MockReport {
+    pub num_failures: usize,
+    pub groups: Vec<FailureGroup>,
+    #[serde(skip)]
+    pub failures: Vec<VerifyFailure>,
+}
+
+/// Failures of the same kind in the same gate and region.
+#[derive(Debug, Serialize)]
+pub struct FailureGroup {
+    /// `constraint`, `copy constraint`, `lookup`, `unassigned cell`, `poisoned constraint`, or `other`
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub failures: Vec<FailureEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailureEntry {
+    /// Offset in the region, or row if outside any region
+    pub location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<String>,
+    /// Values of the cells queried by the failed constraint
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cell_values: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_instance: Option<PublicInstance>,
+    /// The failure as formatted by halo2, for failures that are not broken down further
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// The public instance that a copy constraint failure on an instance column refers to.
+#[derive(Debug, Serialize)]
+pub struct PublicInstance {
+    /// Index of the instance column. The scaffold only uses column 0, whose values are the ones pushed to `make_public`
+    pub column: usize,
+    /// Row in the instance column, which is the index into `make_public` for column 0
+    pub index: usize,
+    pub value: String,
+}
+
+impl MockReport {
+    /// Builds the report from the failures returned by `MockProver::verify`. `instances` are the public instances the mock prover was run with.
+    pub fn new<F: ScalarField>(failures: Vec<VerifyFailure>, instances: &[Vec<F>]) -> Self {
+        let mut groups: Vec<FailureGroup> = vec![];
+        let mut group_index = HashMap::new();
+        for failure in &failures {
+            let (kind, gate, region, entry) = describe(failure, instances);
+            let index = *group_index
+                .entry((kind.clone(), gate.clone(), region.clone()))
+                .or_insert_with(|| {
+                    groups.push(FailureGroup { kind, gate, region, failures: vec![] });
+                    groups.len() - 1
+                });
+            groups[index].failures.push(entry);
+        }
+        Self { num_failures: failures.len(), groups, failures }
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), ScaffoldError> {
+        let writer = BufWriter::new(File::create(path).map_err(ScaffoldError::io(path))?);
+        serde_json::to_writer_pretty(writer, self).map_err(|e| ScaffoldError::io(path)(e.into()))
+    }
+}
+
+/// Splits a failure into its group key `(kind, gate, region)` and its entry.
+fn describe<F: ScalarField>(
+    failure: &VerifyFailure,
+    instances: &[Vec<F>],
+) -> (String, Option<String>, Option<String>, FailureEntry) {
+    let mut entry = FailureEntry {
+        location: String::new(),
+        constraint: None,
+        cell_values: vec![],
+        public_instance: None,
+        message: None,
+    };
+    match failure {
+        VerifyFailure::ConstraintNotSatisfied { constraint, location, cell_values } => {
+            // `metadata::Constraint` only exposes its `Display`, formatted as "Constraint {index} ('{name}') in gate {index} ('{name}')"
+            let constraint = constraint.to_string();
+            let (constraint, gate) = match constraint.split_once(" in gate ") {
+                Some((constraint, gate)) => (constraint.to_string(), Some(format!("gate {gate}"))),
+                None => (constraint, None),
+            };
+            let (region, location) = split_location(location);
+            entry.location = location;
+            entry.constraint = Some(constraint);
+            entry.cell_values =
+                cell_values.iter().map(|(cell, value)| (cell.to_string(), value.clone())).collect();
+            ("constraint".to_string(), gate, region, entry)
+        }
+        VerifyFailure::Permutation { column, location } => {
+            // `metadata::Column` has no public accessors, so the instance column that failed is found by comparing with each of them
+            let instance_column = (0..instances.len())
+                .find(|&i| *column == metadata::Column::from((Any::Instance, i)));
+            if let (Some(instance_column), FailureLocation::OutsideRegion { row }) =
+                (instance_column, location)
+            {
+                entry.public_instance =
+                    instances[instance_column].get(*row).map(|value| PublicInstance {
+                        column: instance_column,
+                        index: *row,
+                        value: format!("{value:?}"),
+                    });
+            }
+            let (region, location) = split_location(location);
+            entry.location = format!("{column} {location}");
+            ("copy constraint".to_string(), None, region, entry)
+        }
+        other => {
+            let kind = match other {
+                VerifyFailure::CellNotAssigned { .. } => "unassigned cell",
+                VerifyFailure::Lookup { .. } => "lookup",
+                VerifyFailure::ConstraintPoisoned { .. } => "poisoned constraint",
+                _ => "other",
+            };
+            entry.message = Some(other.to_string());
+            (kind.to_string(), None, None, entry)
+        }
+    }
+}
+
+fn split_location(location: &FailureLocation) -> (Option<String>, String) {
+    match location {
+        FailureLocation::InRegion { region, offset } => {
+            (Some(region.to_string()), format!("offset {offset}"))
+        }
+        FailureLocation::OutsideRegion { row } => (None, format!("row {row}")),
+    }
+}
+
+impl fmt::Display for MockReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Circuit constraints not satisfied ({} failures in {} groups):",
+            self.num_failures,
+            self.groups.len()
+        )?;
+        for group in &self.groups {
+            write!(f, "\n{} failed {} times", group.kind, group.failures.len())?;
+            if let Some(gate) = &group.gate {
+                write!(f, " in {gate}")?;
+            }
+            if let Some(region) = &group.region {
+                write!(f, " in {region}")?;
+            }
+            writeln!(f)?;
+            for entry in group.failures.iter().take(MAX_PRINTED_PER_GROUP) {
+                match &entry.message {
+                    Some(message) => writeln!(f, "  {message}")?,
+                    None => writeln!(f, "  at {}", entry.location)?,
+                }
+                if let Some(constraint) = &entry.constraint {
+                    writeln!(f, "    {constraint}")?;
+                }
+                for (cell, value) in &entry.cell_values {
+                    writeln!(f, "    {cell} = {value}")?;
+                }
+                match &entry.public_instance {
+                    Some(PublicInstance { column: 0, index, value }) => {
+                        writeln!(f, "    public instance of make_public[{index}] = {value}")?
+                    }
+                    Some(PublicInstance { column, index, value }) => writeln!(
+                        f,
+                        "    public instance {index} of instance column {column} = {value}"
+                    )?,
+                    None => {}
+                }
+            }
+            if group.failures.len() > MAX_PRINTED_PER_GROUP {
+                writeln!(f, "  ... and {} more", group.failures.len() - MAX_PRINTED_PER_GROUP)?;
+            }
+        }
+        Ok(())
+    }
+}
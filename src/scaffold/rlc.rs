@@ -0,0 +1,306 @@
+//! Scaffolding for circuits that use the challenge API through `RlcChip` and `RlpChip`, without the keccak columns of [`super::run_eth`].
+//!
+//! The circuit function is run in phase 0 and returns a callback, which is run in phase 1 once the random challenge `gamma` is known.
+//! The callback has access to an `RlcChip` for random linear combinations, which can be used for permutation and multiset arguments.
+use std::marker::PhantomData;
+
+use axiom_eth::{
+    rlp::{
+        builder::{FnSynthesize, RlcCircuitBuilder, RlcThreadBreakPoints, RlcThreadBuilder},
+        rlc::RlcChip,
+        RlpChip, RlpConfig,
+    },
+    util::circuit::{PinnableCircuit, PreCircuit},
+};
+use halo2_base::{
+    gates::builder::{CircuitBuilderStage, GateThreadBuilder},
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        halo2curves::bn256::{Bn256, Fr},
+        plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    },
+    safe_types::RangeChip,
+    utils::ScalarField,
+    AssignedValue, Context,
+};
+use serde::de::DeserializeOwned;
+use snark_verifier_sdk::CircuitExt;
+
+use super::{
+    batch,
+    cmd::{Cli, SnarkCmd},
+    config::{self, RlcConfigPinning, ScaffoldConfigParams, DEFAULT_MINIMUM_ROWS},
+    exit_on_error, read_inputs_or_keygen_inputs, run_cli, run_without_inputs,
+    stats::{self, BuilderStats, CircuitStats},
+    CircuitConfig, CircuitKind, KeygenInputs, ScaffoldError, ScaffoldPreCircuit,
+};
+
+/// `RlcScaffoldCircuit::configure` does not depend on the phase 1 callback, so any callback type can be used to read a verifying key.
+pub(crate) type RlcVerifierCircuit =
+    RlcScaffoldCircuit<Fr, fn(&mut RlcThreadBuilder<Fr>, &RlcChip<Fr>)>;
+
+pub struct RlcScaffold<T, FN, F1> {
+    f: FN,
+    private_inputs: T,
+    config: CircuitConfig,
+    _f1: PhantomData<F1>,
+}
+
+impl<T, FN, F1> PreCircuit for RlcScaffold<T, FN, F1>
+where
+    FN: FnOnce(&mut GateThreadBuilder<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    type Pinning = RlcConfigPinning;
+
+    fn create_circuit(
+        self,
+        stage: CircuitBuilderStage,
+        pinning: Option<Self::Pinning>,
+        params: &ParamsKZG<Bn256>,
+    ) -> impl PinnableCircuit<Fr> {
+        self.create(stage, pinning, params)
+    }
+}
+
+impl<T, FN, F1> RlcScaffold<T, FN, F1>
+where
+    FN: FnOnce(&mut GateThreadBuilder<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    fn create(
+        self,
+        stage: CircuitBuilderStage,
+        pinning: Option<RlcConfigPinning>,
+        params: &ParamsKZG<Bn256>,
+    ) -> RlcScaffoldCircuit<Fr, impl FnSynthesize<Fr>> {
+        let k = params.k() as usize;
+        let (config_params, break_points) = match pinning {
+            Some(pinning) => (pinning.params, Some(pinning.break_points)),
+            None => {
+                let lookup_bits = self.config.lookup_bits.unwrap_or(0);
+                // `check_create` returns an error before this is reached through the scaffold
+                assert!(lookup_bits < k, "lookup bits needs to be less than DEGREE");
+                let minimum_rows = self.config.minimum_rows.unwrap_or(DEFAULT_MINIMUM_ROWS);
+                let config_params = ScaffoldConfigParams {
+                    degree: k as u32,
+                    lookup_bits,
+                    minimum_rows,
+                    ..Default::default()
+                };
+                (config_params, None)
+            }
+        };
+        // makes `lookup_bits()` available to `f`
+        config::set_current(config_params.clone());
+        let mut builder = RlcThreadBuilder::new(stage == CircuitBuilderStage::Prover);
+        let range = RangeChip::default(config_params.lookup_bits);
+        let chip = RlpChip::new(&range, None);
+
+        let mut assigned_instances = vec![];
+        let f_phase1 = (self.f)(
+            &mut builder.gate_builder,
+            &chip,
+            self.private_inputs,
+            &mut assigned_instances,
+        );
+        let synthesize_phase1 = move |builder: &mut RlcThreadBuilder<Fr>, rlc: &RlcChip<Fr>| {
+            let chip = RlpChip::new(&range, Some(rlc));
+            let (ctx_gate, ctx_rlc) = builder.rlc_ctx_pair();
+            (f_phase1)(ctx_gate, ctx_rlc, &chip);
+            if ctx_gate.advice.is_empty() {
+                builder.gate_builder.threads[1].pop();
+            }
+        };
+        if stage != CircuitBuilderStage::Prover {
+            let config_params =
+                with_columns(config_params, builder.clone(), synthesize_phase1.clone());
+            config::set_current(config_params);
+        }
+        let circuit = RlcCircuitBuilder::new(builder, break_points, synthesize_phase1);
+        RlcScaffoldCircuit { circuit, assigned_instances }
+    }
+}
+
+/// Auto-tunes the number of columns of each phase. Phase 1 is run on a copy of the builder with a placeholder challenge,
+/// since the real challenge is only known during proving, but the number of cells does not depend on it.
+fn with_columns(
+    params: ScaffoldConfigParams,
+    mut builder: RlcThreadBuilder<Fr>,
+    synthesize_phase1: impl FnSynthesize<Fr>,
+) -> ScaffoldConfigParams {
+    synthesize_phase1(&mut builder, &RlcChip::new(Fr::zero()));
+    let k = params.degree as usize;
+    let flex_gate_params = builder.gate_builder.config(k, Some(params.minimum_rows));
+    let usable_rows = (1 << k) - params.minimum_rows;
+    let rlc_cells = builder.threads_rlc.iter().map(|ctx| ctx.advice.len()).sum::<usize>();
+    let num_rlc_columns = ((rlc_cells + usable_rows - 1) / usable_rows).max(1);
+    ScaffoldConfigParams { num_rlc_columns, ..params.with_columns(flex_gate_params) }
+}
+
+impl<T, FN, F1> ScaffoldPreCircuit for RlcScaffold<T, FN, F1>
+where
+    FN: FnOnce(&mut GateThreadBuilder<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    const KIND: CircuitKind = CircuitKind::Rlc;
+
+    fn lookup_bits(&self) -> Option<usize> {
+        self.config.lookup_bits
+    }
+
+    fn stats(self, params: &ParamsKZG<Bn256>) -> Result<CircuitStats, ScaffoldError> {
+        let circuit = self.create(CircuitBuilderStage::Mock, None, params);
+        stats::circuit_stats(&circuit, params.k(), |circuit| {
+            let builder = circuit.circuit.builder.borrow();
+            let mut stats = BuilderStats::from_builder(&builder.gate_builder);
+            // RLC cells are assigned in phase 1
+            stats.advice_cells_per_phase[1] +=
+                builder.threads_rlc.iter().map(|ctx| ctx.advice.len()).sum::<usize>();
+            stats.lookup_advice_columns_per_phase = config::current().num_lookup_advice_per_phase;
+            stats
+        })
+    }
+}
+
+/// The circuit created by [`RlcScaffold`]: an `RlcCircuitBuilder` with an instance column for the values pushed to `make_public`.
+pub struct RlcScaffoldCircuit<F: ScalarField, FnPhase1: FnSynthesize<F>> {
+    pub circuit: RlcCircuitBuilder<F, FnPhase1>,
+    pub assigned_instances: Vec<AssignedValue<F>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RlcScaffoldConfig<F: ScalarField> {
+    pub rlp: RlpConfig<F>,
+    pub instance: Column<Instance>,
+}
+
+impl<F: ScalarField, FnPhase1: FnSynthesize<F>> Circuit<F> for RlcScaffoldCircuit<F, FnPhase1> {
+    type Config = RlcScaffoldConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+
+    /// Configures the circuit from the parameters of the current thread, set by `create_circuit` or by loading the pinning.
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = config::current();
+        let rlp = RlpConfig::configure(
+            meta,
+            params.num_rlc_columns,
+            &params.num_advice_per_phase,
+            &params.num_lookup_advice_per_phase,
+            params.num_fixed,
+            params.lookup_bits,
+            params.degree as usize,
+        );
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        RlcScaffoldConfig { rlp, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.rlp.range.load_lookup_table(&mut layouter)?;
+        // we later `take` the builder, so we need to save this value
+        let witness_gen_only = self.circuit.builder.borrow().witness_gen_only();
+        let assigned_advices = self.circuit.two_phase_synthesize(
+            &config.rlp.range.gate,
+            &config.rlp.range.lookup_advice,
+            &config.rlp.range.q_lookup,
+            &config.rlp.rlc,
+            &mut layouter,
+        );
+        if !witness_gen_only {
+            // expose public instances
+            let mut layouter = layouter.namespace(|| "expose");
+            for (i, instance) in self.assigned_instances.iter().enumerate() {
+                let cell = instance.cell.unwrap();
+                let (cell, _) = assigned_advices
+                    .get(&(cell.context_id, cell.offset))
+                    .expect("instance not assigned");
+                layouter.constrain_instance(*cell, config.instance, i);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: ScalarField, FnPhase1: FnSynthesize<F>> CircuitExt<F> for RlcScaffoldCircuit<F, FnPhase1> {
+    fn num_instance(&self) -> Vec<usize> {
+        vec![self.assigned_instances.len()]
+    }
+
+    fn instances(&self) -> Vec<Vec<F>> {
+        vec![self.assigned_instances.iter().map(|v| *v.value()).collect()]
+    }
+}
+
+impl<F: ScalarField, FnPhase1: FnSynthesize<F>> PinnableCircuit<F>
+    for RlcScaffoldCircuit<F, FnPhase1>
+{
+    type Pinning = RlcConfigPinning;
+
+    fn break_points(&self) -> RlcThreadBreakPoints {
+        self.circuit.break_points.borrow().clone()
+    }
+}
+
+pub fn run_rlc<T, FN, F1>(f: FN, cli: Cli)
+where
+    T: KeygenInputs,
+    FN: FnOnce(&mut Context<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1 + Clone + Sync,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    run_rlc_builder(|builder, chip, inp, public| f(builder.main(0), chip, inp, public), cli)
+}
+
+pub fn run_rlc_builder<T, FN, F1>(f: FN, cli: Cli)
+where
+    T: KeygenInputs,
+    FN: FnOnce(&mut GateThreadBuilder<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1
+        + Clone
+        + Sync,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    if let Some(result) = run_without_inputs(CircuitKind::Rlc, &cli) {
+        return exit_on_error(result);
+    }
+    if let SnarkCmd::Prove { batch: Some(pattern), jobs } = &cli.command {
+        let config = CircuitConfig::from_cli(&cli);
+        let make_precircuit =
+            |private_inputs| pre_run_rlc_builder_on_inputs(f.clone(), private_inputs, config);
+        exit_on_error(batch::run_batch_cli(make_precircuit, &cli, pattern, *jobs));
+        return;
+    }
+    let private_inputs = exit_on_error(read_inputs_or_keygen_inputs(&cli));
+    run_rlc_builder_on_inputs(f, cli, private_inputs)
+}
+
+pub fn run_rlc_builder_on_inputs<T, FN, F1>(f: FN, cli: Cli, private_inputs: T)
+where
+    T: DeserializeOwned,
+    FN: FnOnce(&mut GateThreadBuilder<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    let precircuit =
+        pre_run_rlc_builder_on_inputs(f, private_inputs, CircuitConfig::from_cli(&cli));
+    run_cli(precircuit, cli);
+}
+
+pub fn pre_run_rlc_builder_on_inputs<T, FN, F1>(
+    f: FN,
+    private_inputs: T,
+    config: CircuitConfig,
+) -> RlcScaffold<T, FN, F1>
+where
+    FN: FnOnce(&mut GateThreadBuilder<Fr>, &RlpChip<Fr>, T, &mut Vec<AssignedValue<Fr>>) -> F1,
+    F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &RlpChip<Fr>) + Clone,
+{
+    RlcScaffold { f, private_inputs, config, _f1: PhantomData }
+}
@@ -0,0 +1,168 @@
+//! Loading a KZG structured reference string (SRS) produced by a trusted setup ceremony, instead of generating an unsafe one locally with `gen_srs`.
+//!
+//! Two formats are supported:
+//! - halo2 `ParamsKZG` serialized in `SerdeFormat::RawBytes`, such as the files in <https://github.com/han0110/halo2-kzg-srs>
+//! - `.ptau` files from the Perpetual Powers of Tau ceremony, in the format written by `snarkjs`
+//!
+//! An SRS for a larger degree than needed is downsized to the requested degree.
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use halo2_base::halo2_proofs::{
+    arithmetic::g_to_lagrange,
+    halo2curves::{
+        bn256::{Bn256, G1Affine, G2Affine},
+        group::prime::PrimeCurveAffine,
+        pairing::Engine,
+        serde::SerdeObject,
+    },
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+    },
+    SerdeFormat,
+};
+
+use super::ScaffoldError;
+
+/// Reads an SRS of degree at least `k` from `path` and downsizes it to degree `k`.
+/// Files with the `.ptau` extension are imported from the Perpetual Powers of Tau format, all other files are read as halo2 `ParamsKZG`.
+pub fn read_params(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>, ScaffoldError> {
+    let params = if path.extension().map_or(false, |ext| ext == "ptau") {
+        read_ptau(path, k)?
+    } else {
+        read_params_kzg(path, k)?
+    };
+    check_params(path, &params)?;
+    Ok(params)
+}
+
+/// Reads halo2 `ParamsKZG` in `SerdeFormat::RawBytes` and downsizes them to degree `k`.
+pub fn read_params_kzg(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>, ScaffoldError> {
+    let f = File::open(path)
+        .map_err(|source| ScaffoldError::MissingParams { path: path.to_path_buf(), source })?;
+    let mut params = ParamsKZG::<Bn256>::read_custom(&mut BufReader::new(f), SerdeFormat::RawBytes)
+        .map_err(|e| invalid_params(path, format!("not a halo2 KZG SRS: {e}")))?;
+    if params.k() < k {
+        return Err(invalid_params(path, format!("SRS has degree {} < {k}", params.k())));
+    }
+    if params.k() > k {
+        params.downsize(k);
+    }
+    Ok(params)
+}
+
+// Section ids of the `.ptau` file format, see https://github.com/iden3/snarkjs/blob/master/src/powersoftau_new.js
+const PTAU_HEADER: u32 = 1;
+const PTAU_TAU_G1: u32 = 2;
+const PTAU_TAU_G2: u32 = 3;
+/// Number of bytes of a BN254 base field element
+const N8: usize = 32;
+
+/// Imports the first `2^k` powers of tau from a `.ptau` file.
+///
+/// `snarkjs` stores curve points as uncompressed affine coordinates, each a field element in Montgomery form with little endian 64-bit limbs.
+/// This is the same as the raw byte representation used by `halo2curves`, so the points can be read directly.
+pub fn read_ptau(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>, ScaffoldError> {
+    let f = File::open(path)
+        .map_err(|source| ScaffoldError::MissingParams { path: path.to_path_buf(), source })?;
+    let mut reader = BufReader::new(f);
+    let invalid = |e: io::Error| invalid_params(path, format!("not a .ptau file: {e}"));
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).map_err(invalid)?;
+    if &magic != b"ptau" {
+        return Err(invalid_params(path, "missing .ptau magic bytes".to_string()));
+    }
+    let _version = read_u32(&mut reader).map_err(invalid)?;
+    let num_sections = read_u32(&mut reader).map_err(invalid)?;
+    // (section id, offset of section data)
+    let mut sections = Vec::with_capacity(num_sections as usize);
+    for _ in 0..num_sections {
+        let id = read_u32(&mut reader).map_err(invalid)?;
+        let size = read_u64(&mut reader).map_err(invalid)?;
+        let offset = reader.stream_position().map_err(invalid)?;
+        sections.push((id, offset));
+        reader.seek(SeekFrom::Current(size as i64)).map_err(invalid)?;
+    }
+    let seek_section = |reader: &mut BufReader<File>, id: u32| {
+        let (_, offset) = sections
+            .iter()
+            .find(|(section_id, _)| *section_id == id)
+            .ok_or_else(|| invalid_params(path, format!("missing section {id}")))?;
+        reader.seek(SeekFrom::Start(*offset)).map_err(invalid)
+    };
+
+    seek_section(&mut reader, PTAU_HEADER)?;
+    let n8 = read_u32(&mut reader).map_err(invalid)? as usize;
+    if n8 != N8 {
+        return Err(invalid_params(path, format!("field elements have {n8} bytes, expected {N8}")));
+    }
+    reader.seek(SeekFrom::Current(n8 as i64)).map_err(invalid)?; // modulus
+    let power = read_u32(&mut reader).map_err(invalid)?;
+    if power < k {
+        return Err(invalid_params(path, format!("SRS has degree {power} < {k}")));
+    }
+
+    let n = 1usize << k;
+    seek_section(&mut reader, PTAU_TAU_G1)?;
+    let g = (0..n)
+        .map(|_| read_point::<G1Affine>(&mut reader, 2 * N8))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid_params(path, format!("invalid tau G1 point: {e}")))?;
+    seek_section(&mut reader, PTAU_TAU_G2)?;
+    let [g2, s_g2] = [(); 2].map(|_| read_point::<G2Affine>(&mut reader, 4 * N8));
+    let (g2, s_g2) = g2
+        .and_then(|g2| Ok((g2, s_g2?)))
+        .map_err(|e| invalid_params(path, format!("invalid tau G2 point: {e}")))?;
+
+    let g_lagrange = g_to_lagrange(g.iter().map(|g| g.to_curve()).collect(), k);
+    // assemble the `SerdeFormat::RawBytes` serialization of `ParamsKZG`, so that we do not depend on its private fields
+    let mut bytes = Vec::with_capacity(4 + (2 * n + 4) * 2 * N8);
+    bytes.extend_from_slice(&k.to_le_bytes());
+    for point in g.iter().chain(g_lagrange.iter()) {
+        bytes.extend(point.to_raw_bytes());
+    }
+    bytes.extend(g2.to_raw_bytes());
+    bytes.extend(s_g2.to_raw_bytes());
+    ParamsKZG::<Bn256>::read_custom(&mut &bytes[..], SerdeFormat::RawBytes)
+        .map_err(|e| invalid_params(path, format!("could not assemble SRS: {e}")))
+}
+
+/// Checks that the SRS uses the standard generators and that the G2 element `s * g2` matches the G1 powers of the same `s`, i.e. `e(s * g, g2) = e(g, s * g2)`.
+pub fn check_params(path: &Path, params: &ParamsKZG<Bn256>) -> Result<(), ScaffoldError> {
+    let g = params.get_g();
+    if g[0] != G1Affine::generator() || params.g2() != G2Affine::generator() {
+        return Err(invalid_params(path, "SRS does not use the standard generators".to_string()));
+    }
+    if Bn256::pairing(&g[1], &params.g2()) != Bn256::pairing(&g[0], &params.s_g2()) {
+        return Err(invalid_params(path, "G2 element is inconsistent with G1 powers".to_string()));
+    }
+    Ok(())
+}
+
+fn invalid_params(path: &Path, reason: String) -> ScaffoldError {
+    ScaffoldError::InvalidParams { path: path.to_path_buf(), reason }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_point<C: SerdeObject>(reader: &mut impl Read, len: usize) -> io::Result<C> {
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    C::from_raw_bytes(&buf)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "point is not on the curve"))
+}
@@ -0,0 +1,168 @@
+//! Report of the shape and size of a circuit, for the `stats` command.
+//!
+//! The columns come from the constraint system, the break points from synthesizing the circuit with the mock prover, and the cell counts from the halo2-lib builders.
+//! No keys are generated, so this is cheap enough to track circuit size regressions in CI.
+use std::fmt;
+
+use axiom_eth::util::{circuit::PinnableCircuit, Halo2ConfigPinning};
+use halo2_base::{
+    gates::builder::GateThreadBuilder,
+    halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr, plonk::ConstraintSystem},
+    utils::ScalarField,
+    Context,
+};
+use serde::Serialize;
+
+use super::{degree::CellCounts, ScaffoldError};
+
+/// Cells assigned by the halo2-lib builders of a circuit, which are not visible from its constraint system.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BuilderStats {
+    pub advice_cells_per_phase: Vec<usize>,
+    /// Cells that are range checked with the lookup table, each of which is copied to a lookup advice column
+    pub lookup_cells_per_phase: Vec<usize>,
+    pub lookup_advice_columns_per_phase: Vec<usize>,
+    /// Distinct constants, each of which is stored once in a fixed column
+    pub constants: usize,
+    /// Rows used by the keccak-f permutations of a `KeccakChip`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keccak_rows: Option<usize>,
+}
+
+impl BuilderStats {
+    pub fn from_builder<F: ScalarField>(builder: &GateThreadBuilder<F>) -> Self {
+        let per_phase = |cells: fn(&Context<F>) -> usize| {
+            builder.threads.iter().map(|threads| threads.iter().map(cells).sum()).collect()
+        };
+        Self {
+            advice_cells_per_phase: per_phase(|ctx| ctx.advice.len()),
+            lookup_cells_per_phase: per_phase(|ctx| ctx.cells_to_lookup.len()),
+            constants: CellCounts::from_builder(builder, 0).fixed,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitStats {
+    pub degree: u32,
+    /// `2^degree`
+    pub rows: usize,
+    /// Rows not reserved for blinding factors
+    pub usable_rows: usize,
+    /// Rows used by the most filled phase of advice columns, rounded up, or by keccak
+    pub rows_used: usize,
+    pub advice_columns_per_phase: Vec<usize>,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub lookup_arguments: usize,
+    /// Advice and lookup cells assigned by the halo2-lib builders, plus distinct constants
+    pub total_cells: usize,
+    pub num_instances: usize,
+    #[serde(flatten)]
+    pub builder: BuilderStats,
+    /// The circuit configuration and break points, as written to the pinning by keygen
+    pub pinning: serde_json::Value,
+}
+
+/// Synthesizes `circuit` with the mock prover, which determines its break points, and collects its statistics.
+/// `builder_stats` is called after synthesis to count the cells assigned by the halo2-lib builders of the circuit.
+pub fn circuit_stats<C: PinnableCircuit<Fr>>(
+    circuit: &C,
+    k: u32,
+    builder_stats: impl FnOnce(&C) -> BuilderStats,
+) -> Result<CircuitStats, ScaffoldError> {
+    let mut cs = ConstraintSystem::<Fr>::default();
+    C::configure(&mut cs);
+    let instances = circuit.instances();
+    // constraints are not checked, only the assignment is needed
+    MockProver::run(k, circuit, instances.clone())?;
+    let pinning = C::Pinning::from_var(circuit.break_points());
+    let builder = builder_stats(circuit);
+
+    let rows = 1usize << k;
+    let usable_rows = rows - cs.minimum_rows();
+    let mut advice_columns_per_phase = vec![];
+    for phase in cs.advice_column_phase() {
+        let phase = phase as usize;
+        if advice_columns_per_phase.len() <= phase {
+            advice_columns_per_phase.resize(phase + 1, 0);
+        }
+        advice_columns_per_phase[phase] += 1;
+    }
+    let ceil_div = |cells: usize, columns: usize| {
+        if columns == 0 {
+            0
+        } else {
+            (cells + columns - 1) / columns
+        }
+    };
+    // lookup advice columns are advice columns too, but only hold copies of the lookup cells
+    let gate_columns = advice_columns_per_phase.iter().enumerate().map(|(phase, &columns)| {
+        columns - builder.lookup_advice_columns_per_phase.get(phase).copied().unwrap_or(0)
+    });
+    let gate_rows = builder.advice_cells_per_phase.iter().copied().zip(gate_columns);
+    let lookup_rows = builder
+        .lookup_cells_per_phase
+        .iter()
+        .copied()
+        .zip(builder.lookup_advice_columns_per_phase.iter().copied());
+    let rows_used = gate_rows
+        .chain(lookup_rows)
+        .map(|(cells, columns)| ceil_div(cells, columns))
+        .chain(builder.keccak_rows)
+        .max()
+        .unwrap_or(0);
+    let total_cells = builder.advice_cells_per_phase.iter().sum::<usize>()
+        + builder.lookup_cells_per_phase.iter().sum::<usize>()
+        + builder.constants;
+
+    Ok(CircuitStats {
+        degree: k,
+        rows,
+        usable_rows,
+        rows_used,
+        advice_columns_per_phase,
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        selectors: cs.num_selectors(),
+        lookup_arguments: cs.lookups().len(),
+        total_cells,
+        num_instances: instances.iter().map(Vec::len).sum(),
+        builder,
+        pinning: serde_json::to_value(pinning).expect("pinning should serialize to JSON"),
+    })
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows_used_percent = 100.0 * self.rows_used as f64 / self.rows as f64;
+        writeln!(f, "{:<32}{}", "degree", self.degree)?;
+        writeln!(
+            f,
+            "{:<32}{} / {} ({rows_used_percent:.1}%)",
+            "rows used", self.rows_used, self.rows
+        )?;
+        writeln!(f, "{:<32}{}", "usable rows", self.usable_rows)?;
+        writeln!(f, "{:<32}{:?}", "advice columns per phase", self.advice_columns_per_phase)?;
+        writeln!(
+            f,
+            "{:<32}{:?}",
+            "lookup advice columns per phase", self.builder.lookup_advice_columns_per_phase
+        )?;
+        writeln!(f, "{:<32}{}", "fixed columns", self.fixed_columns)?;
+        writeln!(f, "{:<32}{}", "instance columns", self.instance_columns)?;
+        writeln!(f, "{:<32}{}", "selectors", self.selectors)?;
+        writeln!(f, "{:<32}{}", "lookup arguments", self.lookup_arguments)?;
+        writeln!(f, "{:<32}{:?}", "advice cells per phase", self.builder.advice_cells_per_phase)?;
+        writeln!(f, "{:<32}{:?}", "lookup cells per phase", self.builder.lookup_cells_per_phase)?;
+        writeln!(f, "{:<32}{}", "constants", self.builder.constants)?;
+        if let Some(keccak_rows) = self.builder.keccak_rows {
+            writeln!(f, "{:<32}{keccak_rows}", "keccak rows")?;
+        }
+        writeln!(f, "{:<32}{}", "total cells", self.total_cells)?;
+        writeln!(f, "{:<32}{}", "public instances", self.num_instances)?;
+        write!(f, "{:<32}{}", "break points", self.pinning["break_points"])
+    }
+}
@@ -0,0 +1,864 @@
+//! Helpers to test circuits beyond running the mock prover on a single input.
+//!
+//! ## Negative soundness tests
+//!
+//! Tamper with the witness of a circuit one advice cell at a time, and report every tampered witness the mock prover still accepts.
+//! A cell whose value can change without breaking any constraint is free, which usually means a gate or copy constraint is missing.
+//! Some free cells are expected, e.g. the inverse witnessed by an is-zero check of 0, so the report lists the cells for review instead of failing outright.
+//! Use [`TamperReport::assert_sound`] in tests of circuits that should have no free cells.
+//!
+//! [`tamper_circuit`] takes any raw halo2 [`Circuit`], like the ones in `src/circuits`, and tampers with its `assign_advice` calls.
+//! [`tamper`] and [`tamper_builder`] take the same functions as [`super::run`] and [`super::run_builder`], and tamper with the cells of the `Context`s.
+//!
+//! ## Differential tests
+//!
+//! [`check_against_native`] runs a circuit function on many random inputs and compares its public instances with a native Rust reference implementation.
+//! A failing input is shrunk to a minimal counterexample, as in property-based testing.
+//! Inputs are generated by implementing [`Arbitrary`].
+use std::{
+    any::Any,
+    cell::RefCell,
+    fmt,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+};
+
+use halo2_base::{
+    gates::builder::{CircuitBuilderStage, GateThreadBuilder},
+    halo2_proofs::{
+        arithmetic::Field as _, dev::MockProver as ScaffoldMockProver, halo2curves::bn256::Fr,
+        plonk::Assigned as ScaffoldAssigned,
+    },
+    AssignedValue, Context,
+};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{FloorPlanner, Layouter, Value},
+    dev::MockProver,
+    halo2curves::FieldExt,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
+        Fixed, Instance, Selector,
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use snark_verifier_sdk::CircuitExt;
+
+use super::{
+    pre_run_builder_on_inputs, report::MockReport, CircuitConfig, CircuitScaffold, ScaffoldError,
+    ScaffoldPreCircuit, Witness,
+};
+
+/// Which tampered witnesses to try.
+#[derive(Clone, Copy, Debug)]
+pub enum Tamper {
+    /// Adds one to each advice cell in turn
+    EachCell,
+    /// Replaces each advice cell `x` in turn with `1 - x`, which negates the cells that are booleans, e.g. the output of a comparison
+    Flip,
+    /// Replaces `samples` advice cells chosen at random with random values. The choices only depend on `seed`, so that a report can be reproduced
+    Random { samples: usize, seed: u64 },
+}
+
+impl Tamper {
+    /// The index of the cell to tamper with and how, for each tampered witness to try.
+    fn targets(self, num_cells: usize) -> Vec<(usize, Perturbation)> {
+        match self {
+            Self::EachCell => (0..num_cells).map(|cell| (cell, Perturbation::AddOne)).collect(),
+            Self::Flip => (0..num_cells).map(|cell| (cell, Perturbation::OneMinus)).collect(),
+            Self::Random { .. } if num_cells == 0 => vec![],
+            Self::Random { samples, seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..samples)
+                    .map(|_| (rng.gen_range(0..num_cells), Perturbation::Random(rng.gen())))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Perturbation {
+    AddOne,
+    OneMinus,
+    /// A random value generated from this seed
+    Random(u64),
+}
+
+impl Perturbation {
+    fn apply<F: Field>(self, value: F) -> F {
+        match self {
+            Self::AddOne => value + F::one(),
+            Self::OneMinus => F::one() - value,
+            Self::Random(seed) => F::random(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Same as `apply`, for the field of `halo2-lib` circuits, which implements a different version of the `Field` trait.
+    fn apply_scaffold(self, value: Fr) -> Fr {
+        match self {
+            Self::AddOne => value + Fr::from(1),
+            Self::OneMinus => Fr::from(1) - value,
+            Self::Random(seed) => Fr::random(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+/// A tampered witness that the mock prover accepted.
+#[derive(Clone, Debug)]
+pub struct TamperedCell {
+    /// Where the tampered cell is, e.g. its column, row and region
+    pub location: String,
+    pub original: String,
+    pub tampered: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TamperReport {
+    /// Number of tampered witnesses tried
+    pub num_tampered: usize,
+    /// Tampered witnesses that the mock prover accepted
+    pub accepted: Vec<TamperedCell>,
+}
+
+impl TamperReport {
+    pub fn is_sound(&self) -> bool {
+        self.accepted.is_empty()
+    }
+
+    /// Panics with the list of accepted tampered witnesses, if there are any.
+    pub fn assert_sound(&self) {
+        if !self.is_sound() {
+            panic!("{self}");
+        }
+    }
+}
+
+impl fmt::Display for TamperReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_sound() {
+            return write!(f, "All {} tampered witnesses were rejected", self.num_tampered);
+        }
+        writeln!(
+            f,
+            "{} of {} tampered witnesses were accepted, the following cells may be under-constrained:",
+            self.accepted.len(),
+            self.num_tampered
+        )?;
+        for cell in &self.accepted {
+            writeln!(f, "  {}: {} -> {}", cell.location, cell.original, cell.tampered)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the mock prover on `circuit` with each tampered witness chosen by `tamper`, and reports the ones that are accepted.
+///
+/// Panics if the mock prover rejects the honest witness, since tampering is meaningless then.
+pub fn tamper_circuit<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    instances: Vec<Vec<F>>,
+    tamper: Tamper,
+) -> TamperReport {
+    let circuit = TamperCircuit(circuit);
+    STATE.with(|state| *state.borrow_mut() = TamperState { recording: true, ..Default::default() });
+    MockProver::run(k, &circuit, instances.clone()).unwrap().assert_satisfied();
+    let cells = STATE.with(|state| std::mem::take(&mut state.borrow_mut().cells));
+
+    let mut report = TamperReport::default();
+    for (index, perturbation) in tamper.targets(cells.len()) {
+        STATE.with(|state| {
+            *state.borrow_mut() =
+                TamperState { target: Some((index, perturbation)), ..Default::default() }
+        });
+        // a synthesis error also counts as rejecting the witness
+        let accepted = MockProver::run(k, &circuit, instances.clone())
+            .map_or(false, |prover| prover.verify().is_ok());
+        let tampered = STATE.with(|state| state.borrow_mut().tampered.take());
+        report.num_tampered += 1;
+        if accepted {
+            let cell = &cells[index];
+            report.accepted.push(TamperedCell {
+                location: cell.location.clone(),
+                original: cell.value.clone(),
+                tampered: tampered.unwrap_or_default(),
+            });
+        }
+    }
+    STATE.with(|state| *state.borrow_mut() = TamperState::default());
+    report
+}
+
+/// An advice cell assigned by the honest run of [`tamper_circuit`].
+struct RecordedCell {
+    location: String,
+    value: String,
+}
+
+/// `FloorPlanner::synthesize` cannot be passed any state, so the cell to tamper with is kept in a thread local, like the circuit configuration in `super::config`.
+#[derive(Default)]
+struct TamperState {
+    /// Whether to record every advice cell assigned
+    recording: bool,
+    cells: Vec<RecordedCell>,
+    /// The index of the `assign_advice` call to tamper with, in the order of the calls
+    target: Option<(usize, Perturbation)>,
+    /// The tampered value, once it has been assigned
+    tampered: Option<String>,
+}
+
+thread_local! {
+    static STATE: RefCell<TamperState> = RefCell::new(TamperState::default());
+}
+
+/// Runs the circuit with the floor planner wrapped in a [`TamperFloorPlanner`].
+struct TamperCircuit<'c, C>(&'c C);
+
+impl<'c, F: Field, C: Circuit<F>> Circuit<F> for TamperCircuit<'c, C> {
+    type Config = C::Config;
+    type FloorPlanner = TamperFloorPlanner<C::FloorPlanner>;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.0.synthesize(config, layouter)
+    }
+}
+
+/// Lays out the circuit with the floor planner `P`, but intercepts the assignments to tamper with them.
+struct TamperFloorPlanner<P>(PhantomData<P>);
+
+impl<P: FloorPlanner> FloorPlanner for TamperFloorPlanner<P> {
+    fn synthesize<F: Field, CS: Assignment<F>, C: Circuit<F>>(
+        cs: &mut CS,
+        circuit: &C,
+        config: C::Config,
+        constants: Vec<Column<Fixed>>,
+    ) -> Result<(), Error> {
+        let mut cs = TamperAssignment { cs, region: None, num_assigned: 0 };
+        P::synthesize(&mut cs, circuit, config, constants)
+    }
+}
+
+/// Forwards everything to `cs`, except that the targeted advice assignment is tampered with.
+struct TamperAssignment<'a, CS> {
+    cs: &'a mut CS,
+    region: Option<String>,
+    num_assigned: usize,
+}
+
+impl<'a, F: Field, CS: Assignment<F>> Assignment<F> for TamperAssignment<'a, CS> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name: String = name_fn().into();
+        self.region = Some(name.clone());
+        self.cs.enter_region(|| name);
+    }
+
+    fn annotate_column<A, AR>(&mut self, annotation: A, column: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.annotate_column(annotation, column)
+    }
+
+    fn exit_region(&mut self) {
+        self.region = None;
+        self.cs.exit_region()
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        annotation: A,
+        selector: &Selector,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.enable_selector(annotation, selector, row)
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        self.cs.query_instance(column, row)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let annotation: String = annotation().into();
+        let index = self.num_assigned;
+        self.num_assigned += 1;
+        let mut value: Value<Assigned<F>> = to().map(Into::into);
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            if state.recording {
+                let region = self.region.as_deref().unwrap_or("outside any region");
+                let location = format!(
+                    "advice column {} row {row} ('{annotation}' in region '{region}')",
+                    column.index()
+                );
+                let mut recorded = String::new();
+                value = value.map(|value| {
+                    recorded = format!("{:?}", value.evaluate());
+                    value
+                });
+                state.cells.push(RecordedCell { location, value: recorded });
+            }
+            if let Some((target, perturbation)) = state.target {
+                if target == index {
+                    let mut tampered = None;
+                    value = value.map(|value| {
+                        let value = perturbation.apply(value.evaluate());
+                        tampered = Some(format!("{value:?}"));
+                        Assigned::from(value)
+                    });
+                    state.tampered = tampered;
+                }
+            }
+        });
+        self.cs.assign_advice(|| annotation, column, row, || value)
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.assign_fixed(annotation, column, row, to)
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.cs.copy(left_column, left_row, right_column, right_row)
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        row: usize,
+        to: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        self.cs.fill_from_row(column, row, to)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<F> {
+        self.cs.get_challenge(challenge)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.cs.pop_namespace(gadget_name)
+    }
+}
+
+/// Same as [`tamper_builder`], for functions passed to [`super::run`].
+pub fn tamper<T>(
+    f: impl FnOnce(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+    private_inputs: T,
+    config: CircuitConfig,
+    tamper: Tamper,
+) -> Result<TamperReport, ScaffoldError> {
+    tamper_builder(
+        |builder, inp, public| f(builder.main(0), inp, public),
+        private_inputs,
+        config,
+        tamper,
+    )
+}
+
+/// Runs witness generation of `f` once, then runs the mock prover with each tampered witness chosen by `tamper`, and reports the ones that are accepted.
+/// The degree of the circuit is chosen automatically.
+///
+/// Returns an error if the mock prover rejects the honest witness.
+pub fn tamper_builder<T>(
+    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+    private_inputs: T,
+    config: CircuitConfig,
+    tamper: Tamper,
+) -> Result<TamperReport, ScaffoldError> {
+    let (precircuit, k) = pre_run_builder_on_inputs(f, private_inputs, config).auto_degree()?;
+    let Witness::Generated { builder, assigned_instances } = precircuit.witness else {
+        unreachable!("auto_degree runs witness generation")
+    };
+    let create = |builder| {
+        let witness =
+            Witness::Generated { builder, assigned_instances: assigned_instances.clone() };
+        GeneratedScaffold { witness, config }.create(CircuitBuilderStage::Mock, None, k)
+    };
+
+    let circuit = create(builder.clone());
+    let instances = circuit.instances();
+    ScaffoldMockProver::run(k, &circuit, instances.clone())?
+        .verify()
+        .map_err(|failures| ScaffoldError::Unsatisfied(MockReport::new(failures, &instances)))?;
+
+    // the cells in the order of the contexts of each phase, as (phase, thread, index in the context)
+    let cells = builder
+        .threads
+        .iter()
+        .enumerate()
+        .flat_map(|(phase, threads)| {
+            threads.iter().enumerate().flat_map(move |(thread, ctx)| {
+                (0..ctx.advice.len()).map(move |index| (phase, thread, index))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = TamperReport::default();
+    for (cell, perturbation) in tamper.targets(cells.len()) {
+        let (phase, thread, index) = cells[cell];
+        let mut builder = builder.clone();
+        let advice = &mut builder.threads[phase][thread].advice[index];
+        let original = advice.evaluate();
+        let tampered = perturbation.apply_scaffold(original);
+        *advice = ScaffoldAssigned::Trivial(tampered);
+
+        let circuit = create(builder);
+        let accepted = ScaffoldMockProver::run(k, &circuit, instances.clone())
+            .map_or(false, |prover| prover.verify().is_ok());
+        report.num_tampered += 1;
+        if accepted {
+            report.accepted.push(TamperedCell {
+                location: format!("phase {phase}, thread {thread}, cell {index}"),
+                original: format!("{original:?}"),
+                tampered: format!("{tampered:?}"),
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// A [`CircuitScaffold`] whose witness is already generated, so the types of the inputs and the function do not matter.
+type GeneratedScaffold =
+    CircuitScaffold<(), fn(&mut GateThreadBuilder<Fr>, (), &mut Vec<AssignedValue<Fr>>)>;
+
+/// Random inputs of a circuit function for differential tests, and how to shrink them to a minimal counterexample.
+pub trait Arbitrary: Clone + fmt::Debug {
+    fn arbitrary(rng: &mut StdRng) -> Self;
+
+    /// Simpler values to try in place of `self` when it is a counterexample, simplest first. Each must be strictly simpler, so that shrinking ends.
+    fn shrink(&self) -> Vec<Self> {
+        vec![]
+    }
+}
+
+impl Arbitrary for bool {
+    fn arbitrary(rng: &mut StdRng) -> Self {
+        rng.gen()
+    }
+
+    fn shrink(&self) -> Vec<Self> {
+        if *self {
+            vec![false]
+        } else {
+            vec![]
+        }
+    }
+}
+
+macro_rules! impl_arbitrary_uint {
+    ($($t:ty),*) => {
+        $(
+            impl Arbitrary for $t {
+                fn arbitrary(rng: &mut StdRng) -> Self {
+                    rng.gen()
+                }
+
+                fn shrink(&self) -> Vec<Self> {
+                    let mut shrunk = vec![0, *self / 2, self.saturating_sub(1)];
+                    shrunk.dedup();
+                    shrunk.retain(|x| x < self);
+                    shrunk
+                }
+            }
+        )*
+    };
+}
+
+impl_arbitrary_uint!(u8, u16, u32, u64, u128, usize);
+
+impl Arbitrary for Fr {
+    fn arbitrary(rng: &mut StdRng) -> Self {
+        Fr::random(rng)
+    }
+
+    fn shrink(&self) -> Vec<Self> {
+        [Fr::zero(), Fr::from(1)].into_iter().take_while(|x| x != self).collect()
+    }
+}
+
+/// Maximum length of random vectors, to keep the circuits small.
+const MAX_ARBITRARY_LEN: usize = 16;
+
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary(rng: &mut StdRng) -> Self {
+        let len = rng.gen_range(0..=MAX_ARBITRARY_LEN);
+        (0..len).map(|_| T::arbitrary(rng)).collect()
+    }
+
+    /// Tries the empty vector, the first half, each vector with one element removed, then each vector with one element shrunk.
+    fn shrink(&self) -> Vec<Self> {
+        let mut shrunk = vec![];
+        if !self.is_empty() {
+            shrunk.push(vec![]);
+        }
+        if self.len() > 1 {
+            shrunk.push(self[..self.len() / 2].to_vec());
+        }
+        for i in 0..self.len() {
+            let mut removed = self.clone();
+            removed.remove(i);
+            shrunk.push(removed);
+        }
+        for (i, x) in self.iter().enumerate() {
+            for x in x.shrink() {
+                let mut replaced = self.clone();
+                replaced[i] = x;
+                shrunk.push(replaced);
+            }
+        }
+        shrunk
+    }
+}
+
+impl<T: Arbitrary, const N: usize> Arbitrary for [T; N] {
+    fn arbitrary(rng: &mut StdRng) -> Self {
+        std::array::from_fn(|_| T::arbitrary(rng))
+    }
+
+    fn shrink(&self) -> Vec<Self> {
+        let mut shrunk = vec![];
+        for (i, x) in self.iter().enumerate() {
+            for x in x.shrink() {
+                let mut replaced = self.clone();
+                replaced[i] = x;
+                shrunk.push(replaced);
+            }
+        }
+        shrunk
+    }
+}
+
+impl<A: Arbitrary, B: Arbitrary> Arbitrary for (A, B) {
+    fn arbitrary(rng: &mut StdRng) -> Self {
+        (A::arbitrary(rng), B::arbitrary(rng))
+    }
+
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b) = self;
+        let mut shrunk = a.shrink().into_iter().map(|a| (a, b.clone())).collect::<Vec<_>>();
+        shrunk.extend(b.shrink().into_iter().map(|b| (a.clone(), b)));
+        shrunk
+    }
+}
+
+impl<A: Arbitrary, B: Arbitrary, C: Arbitrary> Arbitrary for (A, B, C) {
+    fn arbitrary(rng: &mut StdRng) -> Self {
+        (A::arbitrary(rng), B::arbitrary(rng), C::arbitrary(rng))
+    }
+
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b, c) = self;
+        let mut shrunk =
+            a.shrink().into_iter().map(|a| (a, b.clone(), c.clone())).collect::<Vec<_>>();
+        shrunk.extend(b.shrink().into_iter().map(|b| (a.clone(), b, c.clone())));
+        shrunk.extend(c.shrink().into_iter().map(|c| (a.clone(), b.clone(), c)));
+        shrunk
+    }
+}
+
+/// Options of [`check_against_native`].
+#[derive(Clone, Copy, Debug)]
+pub struct DifferentialTest {
+    /// Number of random inputs to try
+    pub cases: usize,
+    /// Seed of the random inputs, so that a failure can be reproduced
+    pub seed: u64,
+    /// Maximum number of shrinking steps of a failing input
+    pub max_shrinks: usize,
+    pub config: CircuitConfig,
+}
+
+impl Default for DifferentialTest {
+    fn default() -> Self {
+        Self { cases: 32, seed: 0, max_shrinks: 256, config: CircuitConfig::default() }
+    }
+}
+
+/// Why the circuit disagrees with the native implementation on an input.
+#[derive(Debug)]
+pub enum DifferentialFailure {
+    /// The circuit function or the native implementation panicked, e.g. on an `assert_eq!` inside the circuit function
+    Panic(String),
+    /// Creating the circuit failed, or the mock prover rejected it
+    Circuit(ScaffoldError),
+    /// The public instances of the circuit differ from the output of the native implementation
+    Mismatch { circuit: Vec<Fr>, native: Vec<Fr> },
+}
+
+impl fmt::Display for DifferentialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panic(message) => write!(f, "panicked: {message}"),
+            Self::Circuit(e) => write!(f, "{e}"),
+            Self::Mismatch { circuit, native } => {
+                write!(f, "public instances {circuit:?} differ from the native output {native:?}")
+            }
+        }
+    }
+}
+
+/// A minimal input on which the circuit disagrees with the native implementation.
+#[derive(Debug)]
+pub struct Counterexample<T> {
+    pub input: T,
+    pub failure: DifferentialFailure,
+    /// Index of the random input that first failed, before shrinking
+    pub case: usize,
+    /// Number of shrinking steps from that input to `input`
+    pub num_shrinks: usize,
+}
+
+impl<T: fmt::Debug> fmt::Display for Counterexample<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Circuit disagrees with the native implementation on input {:?}", self.input)?;
+        writeln!(f, "  {}", self.failure)?;
+        write!(f, "  (random case {}, shrunk {} times)", self.case, self.num_shrinks)
+    }
+}
+
+/// Runs witness generation and the mock prover on `test.cases` random inputs of `f`, and checks that the public instances of each are the output of `g` on the same input.
+/// Returns the first failing input, shrunk to a minimal counterexample.
+///
+/// `f` is a function passed to [`super::run`], and `g` is a native implementation of the same computation.
+pub fn check_against_native<T: Arbitrary>(
+    f: impl FnOnce(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone,
+    g: impl Fn(T) -> Vec<Fr>,
+    test: DifferentialTest,
+) -> Result<(), Counterexample<T>> {
+    check_builder_against_native(
+        move |builder, inp, public| f(builder.main(0), inp, public),
+        g,
+        test,
+    )
+}
+
+/// Same as [`check_against_native`], for functions passed to [`super::run_builder`].
+pub fn check_builder_against_native<T: Arbitrary>(
+    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone,
+    g: impl Fn(T) -> Vec<Fr>,
+    test: DifferentialTest,
+) -> Result<(), Counterexample<T>> {
+    let check = |input: &T| check_input(f.clone(), &g, input.clone(), test.config);
+    let mut rng = StdRng::seed_from_u64(test.seed);
+    for case in 0..test.cases {
+        let input = T::arbitrary(&mut rng);
+        let Err(failure) = check(&input) else { continue };
+
+        let mut counterexample = Counterexample { input, failure, case, num_shrinks: 0 };
+        'shrink: while counterexample.num_shrinks < test.max_shrinks {
+            for input in counterexample.input.shrink() {
+                if let Err(failure) = check(&input) {
+                    counterexample.input = input;
+                    counterexample.failure = failure;
+                    counterexample.num_shrinks += 1;
+                    continue 'shrink;
+                }
+            }
+            break;
+        }
+        return Err(counterexample);
+    }
+    Ok(())
+}
+
+/// Runs `f` and `g` on `input` and compares their outputs.
+fn check_input<T: Clone>(
+    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+    g: impl Fn(T) -> Vec<Fr>,
+    input: T,
+    config: CircuitConfig,
+) -> Result<(), DifferentialFailure> {
+    let native = panic::catch_unwind(AssertUnwindSafe(|| g(input.clone())))
+        .map_err(|e| DifferentialFailure::Panic(panic_message(e)))?;
+    let circuit = panic::catch_unwind(AssertUnwindSafe(|| -> Result<_, ScaffoldError> {
+        let (precircuit, k) = pre_run_builder_on_inputs(f, input, config).auto_degree()?;
+        let circuit = precircuit.create(CircuitBuilderStage::Mock, None, k);
+        let instances = circuit.instances();
+        ScaffoldMockProver::run(k, &circuit, instances.clone())?.verify().map_err(|failures| {
+            ScaffoldError::Unsatisfied(MockReport::new(failures, &instances))
+        })?;
+        Ok(instances.into_iter().next().unwrap_or_default())
+    }))
+    .map_err(|e| DifferentialFailure::Panic(panic_message(e)))?
+    .map_err(DifferentialFailure::Circuit)?;
+    if circuit != native {
+        return Err(DifferentialFailure::Mismatch { circuit, native });
+    }
+    Ok(())
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic payload".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::{
+        gates::{GateChip, GateInstructions},
+        QuantumCell::Constant,
+    };
+
+    use super::*;
+
+    /// Adds one to `x`, except that it adds two when `x > 100`.
+    fn add_one_wrong(ctx: &mut Context<Fr>, x: u64, make_public: &mut Vec<AssignedValue<Fr>>) {
+        let gate = GateChip::default();
+        let one = if x > 100 { 2 } else { 1 };
+        let x = ctx.load_witness(Fr::from(x));
+        let y = gate.add(ctx, x, Constant(Fr::from(one)));
+        make_public.push(y);
+    }
+
+    #[test]
+    fn test_check_against_native() {
+        let test = DifferentialTest { cases: 8, ..Default::default() };
+        let add_one = |x: u64| vec![Fr::from(x) + Fr::from(1)];
+        check_against_native(
+            |ctx, x: u64, make_public| {
+                let x = ctx.load_witness(Fr::from(x));
+                make_public.push(GateChip::default().add(ctx, x, Constant(Fr::from(1))));
+            },
+            add_one,
+            test,
+        )
+        .unwrap();
+
+        let counterexample = check_against_native(add_one_wrong, add_one, test).unwrap_err();
+        assert_eq!(counterexample.input, 101);
+        assert!(counterexample.num_shrinks > 0);
+        match counterexample.failure {
+            DifferentialFailure::Mismatch { circuit, native } => {
+                assert_eq!(circuit, vec![Fr::from(103)]);
+                assert_eq!(native, vec![Fr::from(102)]);
+            }
+            failure => panic!("unexpected failure: {failure}"),
+        }
+    }
+
+    #[test]
+    fn test_check_against_native_panic() {
+        let counterexample = check_against_native(
+            |ctx, x: u64, make_public| make_public.push(ctx.load_witness(Fr::from(x))),
+            |x: u64| {
+                assert!(x < 50, "native implementation only supports x < 50");
+                vec![Fr::from(x)]
+            },
+            DifferentialTest { cases: 8, ..Default::default() },
+        )
+        .unwrap_err();
+        assert_eq!(counterexample.input, 50);
+        assert!(matches!(counterexample.failure, DifferentialFailure::Panic(_)));
+    }
+
+    #[test]
+    fn test_tamper_free_cell() {
+        // `y` is loaded but never constrained, so it can take any value
+        let report = tamper(
+            |ctx, (x, y): (u64, u64), make_public| {
+                let x = ctx.load_witness(Fr::from(x));
+                ctx.load_witness(Fr::from(y));
+                make_public.push(x);
+            },
+            (3, 4),
+            CircuitConfig::default(),
+            Tamper::EachCell,
+        )
+        .unwrap();
+        assert_eq!(report.num_tampered, 2);
+        assert_eq!(report.accepted.len(), 1);
+        assert_eq!(report.accepted[0].location, "phase 0, thread 0, cell 1");
+        assert!(!report.is_sound());
+    }
+
+    #[test]
+    fn test_tamper_builder_sound() {
+        let report = tamper_builder(
+            |builder, (x, y): (u64, u64), make_public| {
+                let ctx = builder.main(0);
+                let x = ctx.load_witness(Fr::from(x));
+                let y = ctx.load_witness(Fr::from(y));
+                make_public.push(GateChip::default().add(ctx, x, y));
+            },
+            (3, 4),
+            CircuitConfig::default(),
+            Tamper::EachCell,
+        )
+        .unwrap();
+        assert!(report.num_tampered > 2);
+        report.assert_sound();
+    }
+
+    #[test]
+    fn test_shrink() {
+        assert_eq!(3u8.shrink(), vec![0, 1, 2]);
+        assert_eq!(1u8.shrink(), vec![0]);
+        assert!(0u64.shrink().is_empty());
+        assert_eq!(
+            vec![3u8, 0].shrink(),
+            vec![vec![], vec![3], vec![0], vec![3], vec![0, 0], vec![1, 0], vec![2, 0]]
+        );
+        assert!(Vec::<u8>::new().shrink().is_empty());
+        assert_eq!((true, 2u8).shrink(), vec![(false, 2), (true, 0), (true, 1)]);
+        assert!((false, 0u8).shrink().is_empty());
+    }
+}
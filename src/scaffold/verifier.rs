@@ -0,0 +1,141 @@
+//! Verification of a snark using only the verifying key, the circuit pinning and the snark itself.
+//!
+//! The circuit and its private inputs are not needed: the constraint system needed to deserialize the verifying key is recovered from the pinning,
+//! so anyone can verify a proof given the `.vk`, pinning `.json` and `.snark` files.
+use std::{fs::File, io::BufReader, path::Path};
+
+use axiom_eth::util::{AggregationConfigPinning, EthConfigPinning, Halo2ConfigPinning};
+use clap::ValueEnum;
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{Circuit, VerifyingKey},
+    poly::kzg::commitment::ParamsKZG,
+    SerdeFormat,
+};
+use snark_verifier_sdk::{
+    halo2::{aggregation::AggregationCircuit, read_snark},
+    Snark,
+};
+
+use super::{
+    aggregation::check_accumulator,
+    check_expected_instances,
+    cmd::VerifyCli,
+    config::{RlcConfigPinning, ScaffoldConfigPinning},
+    load_params,
+    proof::{self, ProofOptions},
+    read_pinning, CircuitPaths, EthVerifierCircuit, RlcVerifierCircuit, ScaffoldCircuitBuilder,
+    ScaffoldError,
+};
+
+/// The scaffold used to create a circuit, which determines how to recover its constraint system from the pinning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CircuitKind {
+    /// Circuits created with `run` or `run_builder`
+    Range,
+    /// Circuits created with `run_eth` or `run_eth_builder`
+    Eth,
+    /// Circuits created with `run_rlc` or `run_rlc_builder`
+    Rlc,
+    /// The aggregation circuit created by the `aggregate` command
+    Aggregation,
+}
+
+/// Reads the pinning of the circuit and returns its degree. As a side effect, the pinning is loaded into the configuration read by `Circuit::configure`.
+pub fn load_pinning(kind: CircuitKind, paths: &CircuitPaths) -> Result<u32, ScaffoldError> {
+    let degree = match kind {
+        CircuitKind::Range => {
+            let pinning = read_pinning::<ScaffoldConfigPinning>(paths)?;
+            pinning.set_var();
+            pinning.degree()
+        }
+        CircuitKind::Aggregation => {
+            let pinning = read_pinning::<AggregationConfigPinning>(paths)?;
+            pinning.set_var();
+            pinning.degree()
+        }
+        CircuitKind::Eth => {
+            let pinning = read_pinning::<EthConfigPinning>(paths)?;
+            pinning.set_var();
+            pinning.degree()
+        }
+        CircuitKind::Rlc => {
+            let pinning = read_pinning::<RlcConfigPinning>(paths)?;
+            pinning.set_var();
+            pinning.degree()
+        }
+    };
+    Ok(degree)
+}
+
+/// Reads the verifying key of the circuit, using its pinning to recover the constraint system.
+pub fn read_vk(
+    kind: CircuitKind,
+    paths: &CircuitPaths,
+) -> Result<VerifyingKey<G1Affine>, ScaffoldError> {
+    load_pinning(kind, paths)?;
+    let vk_path = paths.vk();
+    match kind {
+        CircuitKind::Range => read_vk_as::<ScaffoldCircuitBuilder<Fr>>(&vk_path),
+        CircuitKind::Eth => read_vk_as::<EthVerifierCircuit>(&vk_path),
+        CircuitKind::Rlc => read_vk_as::<RlcVerifierCircuit>(&vk_path),
+        CircuitKind::Aggregation => read_vk_as::<AggregationCircuit>(&vk_path),
+    }
+}
+
+/// Reads a verifying key for the circuit type `C`. Only `C::configure` is used, so no instance of the circuit is needed.
+pub fn read_vk_as<C: Circuit<Fr>>(path: &Path) -> Result<VerifyingKey<G1Affine>, ScaffoldError> {
+    let f = File::open(path)
+        .map_err(|source| ScaffoldError::MissingKey { path: path.to_path_buf(), source })?;
+    let mut bufreader = BufReader::new(f);
+    VerifyingKey::read::<_, C>(&mut bufreader, SerdeFormat::RawBytes)
+        .map_err(|source| ScaffoldError::KeyMismatch { path: path.to_path_buf(), source })
+}
+
+/// Verifies the snark written by [`super::prove`] against the verifying key written by [`super::keygen`], returning the verified snark.
+/// The verifier matches the transcript and multi-open scheme recorded next to the snark.
+/// For the aggregation circuit, the accumulator in the public instances is checked as well.
+pub fn verify(
+    kind: CircuitKind,
+    params: &ParamsKZG<Bn256>,
+    paths: &CircuitPaths,
+) -> Result<Snark, ScaffoldError> {
+    let vk = read_vk(kind, paths)?;
+    let snark_path = paths.snark();
+    let snark = read_snark(&snark_path)
+        .map_err(|source| ScaffoldError::MissingSnark { path: snark_path.clone(), source })?;
+    let options = ProofOptions::read_for(&snark_path)?;
+    verify_snark(params, &vk, &snark, options)?;
+    if kind == CircuitKind::Aggregation {
+        check_accumulator(params, &snark.instances[0])?;
+    }
+    Ok(snark)
+}
+
+/// Verifies a snark created by [`super::prove`] with the transcript and multi-open scheme of `options`.
+pub fn verify_snark(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    snark: &Snark,
+    options: ProofOptions,
+) -> Result<(), ScaffoldError> {
+    proof::verify_proof_with(params, vk, &snark.proof, &snark.instances, options)
+}
+
+/// Entry point of the standalone `verify` binary. The degree of the circuit is read from its pinning.
+pub fn verify_cli(cli: VerifyCli) -> Result<Snark, ScaffoldError> {
+    let mut paths = CircuitPaths::new(
+        cli.name,
+        cli.config_path.unwrap_or_else(|| "configs".into()),
+        cli.data_path.unwrap_or_else(|| "data".into()),
+    );
+    if cli.kind == CircuitKind::Aggregation {
+        paths = paths.aggregation();
+    }
+    let k = load_pinning(cli.kind, &paths)?;
+    let params = load_params(cli.params_path.as_deref(), k)?;
+    let snark = verify(cli.kind, &params, &paths)?;
+    check_expected_instances(&snark, cli.expect_instances.as_deref())?;
+    println!("Snark verified successfully!");
+    Ok(snark)
+}
@@ -0,0 +1,337 @@
+//! Aggregate queries `COUNT(*)`, `SUM`, `MIN`, `MAX` and `AVG` of a column, with an optional `WHERE` clause.
+//!
+//! A query is read from JSON, e.g. `SELECT AVG(amount) WHERE paid = true` is
+//!
+//! ```json
+//! {
+//!     "function": "avg",
+//!     "column": "amount",
+//!     "where": { "column": "paid", "op": "=", "value": true },
+//!     "avg": "rounded"
+//! }
+//! ```
+//!
+//! `column` is not needed for `count`, and must be a `uint` or `bool` column otherwise. `where` is as in [`super::query`].
+//!
+//! After the table commitment, [`aggregate`] exposes the constants of the `WHERE` clause, the number of rows satisfying it `count`, and then, except for `count`,
+//! the value of the function over these rows. For `avg`, this is the sum, so that the average is the exposed pair `(sum, count)`,
+//! or with `"avg": "rounded"` the average rounded to the nearest integer, with halves rounded up.
+//! SQL returns `NULL` for these functions over no rows, which here is a `count` of 0, with a value of 0.
+use halo2_base::{
+    gates::{GateInstructions, RangeChip, RangeInstructions},
+    utils::{biguint_to_fe, fe_to_biguint, BigPrimeField},
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    query::{filter, Predicate},
+    table::{AssignedTable, Schema, Table, TableError},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Function {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+/// How the result of `AVG` is exposed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Avg {
+    /// The sum, so that the exact average is `sum / count`
+    #[default]
+    Pair,
+    /// `sum / count` rounded to the nearest integer, computed with `RangeInstructions::div_mod_var`
+    Rounded,
+}
+
+/// `SELECT function(column) WHERE filter`, see the top of this file for the JSON format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Aggregate {
+    pub function: Function,
+    /// The aggregated column, not needed for `count`
+    #[serde(default)]
+    pub column: Option<String>,
+    #[serde(default, rename = "where")]
+    pub filter: Option<Predicate>,
+    #[serde(default)]
+    pub avg: Avg,
+}
+
+/// Number of bits of the sum of `num_rows` cells of `bits` bits each.
+/// With at most [`super::table::MAX_UINT_BITS`] bits per cell, this is far below the field size, so sums of cells never wrap around.
+pub fn sum_bits(bits: usize, num_rows: usize) -> usize {
+    bits + bit_length(num_rows)
+}
+
+fn bit_length(n: usize) -> usize {
+    (usize::BITS - n.leading_zeros()) as usize
+}
+
+impl Aggregate {
+    /// Index and bit width of the aggregated column, or `None` for `count`.
+    fn column(&self, schema: &Schema) -> Result<Option<(usize, usize)>, TableError> {
+        if self.function == Function::Count {
+            return Ok(None);
+        }
+        let name = self.column.as_ref().ok_or(TableError::MissingColumn)?;
+        let index = schema.index_of(name)?;
+        let ty = schema.columns[index].ty;
+        let bits = ty.bits().ok_or_else(|| TableError::NotNumeric { column: name.clone(), ty })?;
+        Ok(Some((index, bits)))
+    }
+
+    /// The public instances [`aggregate`] exposes after the table commitment, computed natively.
+    pub fn public_output<F: BigPrimeField>(&self, table: &Table<F>) -> Result<Vec<F>, TableError> {
+        let column = self.column(&table.schema)?;
+        let (mut output, selected) = match &self.filter {
+            Some(predicate) => (predicate.resolve(&table.schema)?.1, predicate.evaluate(table)?),
+            None => (vec![], vec![true; table.rows.len()]),
+        };
+        let count = selected.iter().filter(|selected| **selected).count();
+        output.push(F::from(count as u64));
+        let Some((index, _)) = column else { return Ok(output) };
+
+        let values = table
+            .rows
+            .iter()
+            .zip(selected)
+            .filter(|(_, selected)| *selected)
+            .map(|(row, _)| row[index])
+            .collect::<Vec<_>>();
+        let sum = values.iter().fold(F::zero(), |acc, value| acc + value);
+        let value = match self.function {
+            Function::Count => unreachable!("count has no column"),
+            Function::Sum => sum,
+            Function::Min => values.into_iter().min_by_key(fe_to_biguint).unwrap_or_else(F::zero),
+            Function::Max => values.into_iter().max_by_key(fe_to_biguint).unwrap_or_else(F::zero),
+            Function::Avg => match self.avg {
+                Avg::Pair => sum,
+                Avg::Rounded if count == 0 => F::zero(),
+                Avg::Rounded => {
+                    let count = F::from(count as u64);
+                    biguint_to_fe(
+                        &(fe_to_biguint(&(sum + sum + count)) / fe_to_biguint(&(count + count))),
+                    )
+                }
+            },
+        };
+        output.push(value);
+        Ok(output)
+    }
+}
+
+/// The result of [`aggregate`] in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedAggregate<F: BigPrimeField> {
+    /// Number of rows satisfying the filter
+    pub count: AssignedValue<F>,
+    /// Value of the function over these rows, `None` for `count`
+    pub value: Option<AssignedValue<F>>,
+}
+
+/// Proves the result of `query` over `table` and pushes it to `make_public` as described at the top of this file.
+///
+/// `range` must have been created with a positive number of lookup bits, e.g. [`Schema::lookup_bits`], for `min`, `max`, rounded `avg` and ordered comparisons.
+pub fn aggregate<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    table: &AssignedTable<F>,
+    query: &Aggregate,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<AssignedAggregate<F>, TableError> {
+    let gate = range.gate();
+    let column = query.column(&table.schema)?;
+    let num_rows = table.rows.len();
+    let selected = match &query.filter {
+        Some(predicate) => filter(ctx, range, table, predicate, make_public)?,
+        None => vec![ctx.load_constant(F::one()); num_rows],
+    };
+    let count = gate.sum(ctx, selected.iter().copied());
+    make_public.push(count);
+    let Some((index, bits)) = column else {
+        return Ok(AssignedAggregate { count, value: None });
+    };
+
+    let cells = table.column(index);
+    let value = match query.function {
+        Function::Count => unreachable!("count has no column"),
+        Function::Min => extremum(ctx, range, &cells, &selected, count, bits, false),
+        Function::Max => extremum(ctx, range, &cells, &selected, count, bits, true),
+        Function::Sum | Function::Avg => {
+            let sum_bits = sum_bits(bits, num_rows);
+            debug_assert!(sum_bits < F::CAPACITY as usize);
+            let sum = gate.inner_product(ctx, cells, selected.iter().map(|&bit| Existing(bit)));
+            if query.function == Function::Avg && query.avg == Avg::Rounded {
+                rounded_div(ctx, range, sum, count, sum_bits, num_rows)
+            } else {
+                sum
+            }
+        }
+    };
+    make_public.push(value);
+    Ok(AssignedAggregate { count, value: Some(value) })
+}
+
+/// The minimum, or the maximum if `max`, of the `cells` of `bits` bits whose `selected` bit is set, or 0 if `count` is 0.
+///
+/// The running extremum starts at the value no cell can improve on: `2^bits - 1` for the minimum and 0 for the maximum.
+/// Each selected cell replaces it if it is smaller, or larger, while unselected cells are replaced by the starting value so that they never do.
+fn extremum<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    cells: &[AssignedValue<F>],
+    selected: &[AssignedValue<F>],
+    count: AssignedValue<F>,
+    bits: usize,
+    max: bool,
+) -> AssignedValue<F> {
+    let gate = range.gate();
+    let start = if max { F::zero() } else { F::from_u128(u128::MAX >> (128 - bits)) };
+    let mut extremum = ctx.load_constant(start);
+    for (&cell, &bit) in cells.iter().zip(selected) {
+        let candidate = gate.select(ctx, cell, Constant(start), bit);
+        // both sides are less than 2^bits: cells by the table commitment, and the running extremum because it is a cell or the start
+        let better = if max {
+            range.is_less_than(ctx, extremum, candidate, bits)
+        } else {
+            range.is_less_than(ctx, candidate, extremum, bits)
+        };
+        extremum = gate.select(ctx, candidate, extremum, better);
+    }
+    let empty = gate.is_zero(ctx, count);
+    gate.select(ctx, Constant(F::zero()), extremum, empty)
+}
+
+/// `sum / count` rounded to the nearest integer with halves rounded up, or 0 if `count` is 0.
+/// `sum` has at most `sum_bits` bits, and `count` is at most `num_rows`.
+fn rounded_div<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    sum: AssignedValue<F>,
+    count: AssignedValue<F>,
+    sum_bits: usize,
+    num_rows: usize,
+) -> AssignedValue<F> {
+    let gate = range.gate();
+    // the sum is 0 when `count` is 0, so dividing it by 1 instead gives 0
+    let empty = gate.is_zero(ctx, count);
+    let divisor = gate.add(ctx, count, empty);
+    // round(sum / divisor) = floor((2 * sum + divisor) / (2 * divisor))
+    let numerator = gate.mul_add(ctx, sum, Constant(F::from(2)), divisor);
+    let denominator = gate.add(ctx, divisor, divisor);
+    // the numerator is less than 2^(sum_bits + 2) since `divisor <= num_rows < 2^sum_bits`, and the denominator is at most `2 * max(num_rows, 1)`
+    let (quotient, _) = range.div_mod_var(
+        ctx,
+        numerator,
+        denominator,
+        sum_bits + 2,
+        bit_length(num_rows.max(1)) + 1,
+    );
+    quotient
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use poseidon::PoseidonChip;
+
+    use super::*;
+    use crate::{
+        scaffold::{
+            lookup_bits,
+            testing::{check_against_native, DifferentialTest},
+            CircuitConfig,
+        },
+        sql::{
+            query::Comparison,
+            table::{commit_table, Cell, Column, ColumnType, R_F, R_P},
+        },
+    };
+
+    /// A table with columns `key` and `value`, with keys in `0..4` so that equality filters match some rows.
+    fn table(rows: &[(u8, u8)]) -> Table<Fr> {
+        let column =
+            |name: &str| Column { name: name.to_string(), ty: ColumnType::Uint { bits: 8 } };
+        let schema = Schema { columns: vec![column("key"), column("value")] };
+        let rows =
+            rows.iter().map(|&(key, value)| vec![Fr::from(key as u64 % 4), Fr::from(value as u64)]);
+        Table::new(schema, rows.collect()).unwrap()
+    }
+
+    /// `SELECT function(value) WHERE key = key % 4`, without the filter if `filtered` is false.
+    fn query(function: Function, key: u8, filtered: bool, avg: Avg) -> Aggregate {
+        let filter = filtered.then(|| Predicate {
+            column: "key".to_string(),
+            comparison: Comparison::Eq { value: Cell::Number(key as u64 % 4) },
+        });
+        Aggregate { function, column: Some("value".to_string()), filter, avg }
+    }
+
+    fn random_query((function, key): (u8, u8), (filtered, rounded): (bool, bool)) -> Aggregate {
+        let functions =
+            [Function::Count, Function::Sum, Function::Min, Function::Max, Function::Avg];
+        let avg = if rounded { Avg::Rounded } else { Avg::Pair };
+        query(functions[function as usize % functions.len()], key, filtered, avg)
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let result = check_against_native(
+            |ctx, (rows, q, options): (Vec<(u8, u8)>, _, _), make_public| {
+                let range = RangeChip::default(lookup_bits());
+                let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+                let table = commit_table(ctx, range.gate(), &poseidon, &table(&rows), make_public);
+                aggregate(ctx, &range, &table, &random_query(q, options), make_public).unwrap();
+            },
+            |(rows, q, options)| {
+                let table = table(&rows);
+                let output = random_query(q, options).public_output(&table).unwrap();
+                [vec![table.commitment()], output].concat()
+            },
+            DifferentialTest {
+                config: CircuitConfig { lookup_bits: Some(8), ..Default::default() },
+                ..Default::default()
+            },
+        );
+        if let Err(counterexample) = result {
+            panic!("{counterexample}");
+        }
+    }
+
+    #[test]
+    fn test_aggregate_evaluate() {
+        // 7 / 2 = 3.5 rounds up to 4
+        let halves = table(&[(1, 3), (1, 4)]);
+        let rounded = query(Function::Avg, 1, false, Avg::Rounded).public_output(&halves);
+        assert_eq!(rounded.unwrap(), [2, 4].map(Fr::from));
+
+        let table = table(&[(1, 10), (2, 255), (1, 3), (1, 4)]);
+        let output = |function, key, filtered, avg| {
+            query(function, key, filtered, avg).public_output(&table).unwrap()
+        };
+        assert_eq!(output(Function::Count, 0, false, Avg::Pair), [4].map(Fr::from));
+        assert_eq!(output(Function::Sum, 1, true, Avg::Pair), [1, 3, 17].map(Fr::from));
+        assert_eq!(output(Function::Min, 1, true, Avg::Pair), [1, 3, 3].map(Fr::from));
+        assert_eq!(output(Function::Max, 1, false, Avg::Pair), [4, 255].map(Fr::from));
+        assert_eq!(output(Function::Avg, 1, true, Avg::Pair), [1, 3, 17].map(Fr::from));
+        // 17 / 3 = 5.67 rounds to 6
+        assert_eq!(output(Function::Avg, 1, true, Avg::Rounded), [1, 3, 6].map(Fr::from));
+        assert_eq!(output(Function::Avg, 2, true, Avg::Rounded), [2, 1, 255].map(Fr::from));
+    }
+
+    #[test]
+    fn test_aggregate_empty() {
+        let table = table(&[(1, 10), (2, 20)]);
+        for function in [Function::Sum, Function::Min, Function::Max, Function::Avg] {
+            let output = query(function, 3, true, Avg::Rounded).public_output(&table).unwrap();
+            assert_eq!(output, [3, 0, 0].map(Fr::from));
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! `SELECT key, COUNT(*), SUM(value) GROUP BY key` over a committed table, with one result row per distinct key.
+//!
+//! A query is read from JSON, e.g. `SELECT customer_id, COUNT(*), SUM(amount) GROUP BY customer_id` with room for 4 customers is
+//!
+//! ```json
+//! { "key": "customer_id", "value": "amount", "max_groups": 4 }
+//! ```
+//!
+//! Both columns must be `uint` or `bool` columns. The circuit cannot depend on how many distinct keys there are, so as in `examples/var_len_keccak.rs`,
+//! the groups are padded with zeros to the fixed length `max_groups` and exposed together with their actual number `num_groups`.
+//! After the table commitment, [`group_by`] exposes `num_groups`, then for each of the `max_groups` slots the key, count and sum of a group,
+//! with the groups in increasing key order. A table with more than `max_groups` distinct keys cannot be proven.
+//!
+//! The prover witnesses the rows of the table sorted by key. In phase 0, the circuit checks that the sorted keys never decrease, so that rows with equal keys are adjacent,
+//! and starts a new group at each row whose key differs from the previous one. Every sorted row is then added to exactly one group, and the keys of the groups are distinct
+//! because they strictly increase. The sorted rows are only useful if they are the rows of the table, which [`Permutation::constrain`] checks in phase 1
+//! with the random challenge, so [`group_by`] is used with [`crate::scaffold::run_rlc`].
+use std::{collections::BTreeMap, iter};
+
+use axiom_eth::rlp::rlc::RlcChip;
+use halo2_base::{
+    gates::{GateChip, GateInstructions, RangeChip, RangeInstructions},
+    utils::{fe_to_biguint, BigPrimeField, ScalarField},
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    aggregate::sum_bits,
+    table::{AssignedTable, Schema, Table, TableError},
+};
+
+/// `SELECT key, COUNT(*), SUM(value) GROUP BY key`, see the top of this file for the JSON format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupBy {
+    pub key: String,
+    pub value: String,
+    /// Number of groups the result is padded to, which the circuit depends on
+    pub max_groups: usize,
+}
+
+impl GroupBy {
+    /// Index and bit width of the key column, then of the value column.
+    fn columns<F: BigPrimeField>(
+        &self,
+        schema: &Schema,
+    ) -> Result<[(usize, usize); 2], TableError> {
+        let key = schema.index_of(&self.key)?;
+        let ty = schema.columns[key].ty;
+        let key_bits =
+            ty.bits().ok_or_else(|| TableError::Unordered { column: self.key.clone(), ty })?;
+        let value = schema.index_of(&self.value)?;
+        let ty = schema.columns[value].ty;
+        let value_bits =
+            ty.bits().ok_or_else(|| TableError::NotNumeric { column: self.value.clone(), ty })?;
+        if key_bits + value_bits > F::CAPACITY as usize {
+            return Err(TableError::GroupTooWide {
+                key: self.key.clone(),
+                value: self.value.clone(),
+            });
+        }
+        Ok([(key, key_bits), (value, value_bits)])
+    }
+
+    /// The groups as `[key, count, sum]`, in increasing key order.
+    pub fn evaluate<F: BigPrimeField>(&self, table: &Table<F>) -> Result<Vec<[F; 3]>, TableError> {
+        let [(key, _), (value, _)] = self.columns::<F>(&table.schema)?;
+        let mut groups = BTreeMap::new();
+        for row in &table.rows {
+            let group =
+                groups.entry(fe_to_biguint(&row[key])).or_insert([row[key], F::zero(), F::zero()]);
+            group[1] += F::one();
+            group[2] += row[value];
+        }
+        if groups.len() > self.max_groups {
+            return Err(TableError::TooManyGroups { found: groups.len(), max: self.max_groups });
+        }
+        Ok(groups.into_values().collect())
+    }
+
+    /// The public instances [`group_by`] exposes after the table commitment, computed natively.
+    pub fn public_output<F: BigPrimeField>(&self, table: &Table<F>) -> Result<Vec<F>, TableError> {
+        let groups = self.evaluate(table)?;
+        let mut output = vec![F::from(groups.len() as u64)];
+        let padded = groups.into_iter().chain(iter::repeat([F::zero(); 3])).take(self.max_groups);
+        output.extend(padded.flatten());
+        Ok(output)
+    }
+}
+
+/// Two lists of phase 0 values which must be permutations of each other, checked in phase 1 by [`Permutation::constrain`].
+#[derive(Clone, Debug)]
+pub struct Permutation<F: ScalarField> {
+    pub original: Vec<AssignedValue<F>>,
+    pub permuted: Vec<AssignedValue<F>>,
+}
+
+impl<F: ScalarField> Permutation<F> {
+    /// Constrains `(gamma - x_1) * ... * (gamma - x_n)` to be the same for both lists, where `gamma` is the challenge of `rlc`.
+    ///
+    /// As polynomials in `gamma`, the two products are equal exactly when the lists are permutations of each other.
+    /// Since `gamma` is chosen after the values are committed, different lists give equal products with probability at most `n / |F|`.
+    pub fn constrain(
+        self,
+        ctx_gate: &mut Context<F>,
+        ctx_rlc: &mut Context<F>,
+        gate: &GateChip<F>,
+        rlc: &RlcChip<F>,
+    ) {
+        assert_eq!(self.original.len(), self.permuted.len(), "permutations have the same length");
+        // caches gamma^(2^0) so that gamma itself can be loaded
+        rlc.load_rlc_cache((&mut *ctx_gate, &mut *ctx_rlc), gate, 1);
+        let gamma = rlc.rlc_pow_fixed(ctx_gate, gate, 1);
+        let product = |ctx: &mut Context<F>, values: Vec<AssignedValue<F>>| {
+            let mut product = ctx.load_constant(F::one());
+            for value in values {
+                let factor = gate.sub(ctx, gamma, value);
+                product = gate.mul(ctx, product, factor);
+            }
+            product
+        };
+        let original = product(ctx_gate, self.original);
+        let permuted = product(ctx_gate, self.permuted);
+        ctx_gate.constrain_equal(&original, &permuted);
+    }
+}
+
+/// The result of [`group_by`] in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedGroupBy<F: ScalarField> {
+    pub num_groups: AssignedValue<F>,
+    /// `[key, count, sum]` for each of the `max_groups` slots: the groups in increasing key order, then zeros
+    pub groups: Vec<[AssignedValue<F>; 3]>,
+    /// The table rows and the sorted rows the groups were computed from, which must be constrained in phase 1
+    pub permutation: Permutation<F>,
+}
+
+/// Proves the result of `query` over `table` and pushes it to `make_public` as described at the top of this file.
+///
+/// The result is only proven once `permutation` of the returned value is constrained in the phase 1 callback.
+/// `range` must have been created with a positive number of lookup bits, e.g. [`Schema::lookup_bits`].
+pub fn group_by<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    table: &AssignedTable<F>,
+    query: &GroupBy,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<AssignedGroupBy<F>, TableError> {
+    let [(key, _), (value, _)] = query.columns::<F>(&table.schema)?;
+    // only witness generation: nothing below relies on the prover sorting the rows honestly
+    let mut sorted =
+        table.rows.iter().map(|row| [*row[key].value(), *row[value].value()]).collect::<Vec<_>>();
+    sorted.sort_by_key(|[key, _]| fe_to_biguint(key));
+    group_sorted(ctx, range, table, query, sorted, make_public)
+}
+
+/// [`group_by`] with the rows `[key, value]` the prover claims to be the table rows sorted by key.
+fn group_sorted<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    table: &AssignedTable<F>,
+    query: &GroupBy,
+    sorted: Vec<[F; 2]>,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<AssignedGroupBy<F>, TableError> {
+    let gate = range.gate();
+    let [(key, key_bits), (value, value_bits)] = query.columns::<F>(&table.schema)?;
+    let num_rows = table.rows.len();
+    assert_eq!(sorted.len(), num_rows, "one sorted row per table row");
+
+    // a row is packed into `key * 2^value_bits + value`, which is one to one on cells of these widths, so that rows are permuted as single values.
+    // Table cells have these widths by the commitment, and the sorted cells are range checked.
+    let shift = Constant(gate.pow_of_two()[value_bits]);
+    let original = table.rows.iter().map(|row| gate.mul_add(ctx, row[key], shift, row[value]));
+    let original = original.collect();
+    let (mut keys, mut values, mut permuted) = (vec![], vec![], vec![]);
+    for row in sorted {
+        let [key_cell, value_cell] = row.map(|x| ctx.load_witness(x));
+        range.range_check(ctx, key_cell, key_bits);
+        range.range_check(ctx, value_cell, value_bits);
+        permuted.push(gate.mul_add(ctx, key_cell, shift, value_cell));
+        keys.push(key_cell);
+        values.push(value_cell);
+    }
+
+    // `starts[i]` is 1 if row `i` starts a group, and `numbers[i]` is the 1-based number of the group of row `i`
+    let mut starts =
+        keys.first().map(|_| ctx.load_constant(F::one())).into_iter().collect::<Vec<_>>();
+    for pair in keys.windows(2) {
+        let decreasing = range.is_less_than(ctx, pair[1], pair[0], key_bits);
+        gate.assert_is_const(ctx, &decreasing, &F::zero());
+        let same = gate.is_equal(ctx, pair[1], pair[0]);
+        starts.push(gate.not(ctx, same));
+    }
+    let mut number = ctx.load_zero();
+    let numbers = starts.iter().map(|&start| {
+        number = gate.add(ctx, number, start);
+        number
+    });
+    let numbers = numbers.collect::<Vec<_>>();
+    let num_groups = number;
+    // every row has a group number of at most `num_groups`, so it falls in one of the slots below
+    range.check_less_than_safe(ctx, num_groups, query.max_groups as u64 + 1);
+    make_public.push(num_groups);
+
+    debug_assert!(sum_bits(value_bits, num_rows) < F::CAPACITY as usize);
+    let groups = (1..=query.max_groups as u64)
+        .map(|slot| {
+            let members = numbers
+                .iter()
+                .map(|&number| gate.is_equal(ctx, number, Constant(F::from(slot))))
+                .collect::<Vec<_>>();
+            // the key of a group is the key of its first row
+            let heads = members
+                .iter()
+                .zip(&starts)
+                .map(|(&member, &start)| gate.mul(ctx, member, start))
+                .collect::<Vec<_>>();
+            let key = gate.inner_product(ctx, keys.clone(), heads.into_iter().map(Existing));
+            let count = gate.sum(ctx, members.iter().copied());
+            let sum = gate.inner_product(ctx, values.clone(), members.into_iter().map(Existing));
+            make_public.extend([key, count, sum]);
+            [key, count, sum]
+        })
+        .collect();
+
+    Ok(AssignedGroupBy { num_groups, groups, permutation: Permutation { original, permuted } })
+}
+
+#[cfg(test)]
+mod test {
+    use axiom_eth::rlp::RlpChip;
+    use halo2_base::{
+        gates::builder::GateThreadBuilder, halo2_proofs::halo2curves::bn256::Fr, utils::fs::gen_srs,
+    };
+    use poseidon::PoseidonChip;
+
+    use super::*;
+    use crate::{
+        scaffold::{mock, pre_run_rlc_builder_on_inputs, CircuitConfig, ScaffoldError},
+        sql::table::{commit_table, Column, ColumnType, R_F, R_P},
+    };
+
+    const K: u32 = 11;
+
+    /// A table with columns `key` and `value`.
+    fn table(rows: &[(u8, u8)]) -> Table<Fr> {
+        let column =
+            |name: &str| Column { name: name.to_string(), ty: ColumnType::Uint { bits: 8 } };
+        let schema = Schema { columns: vec![column("key"), column("value")] };
+        let rows =
+            rows.iter().map(|&(key, value)| vec![Fr::from(key as u64), Fr::from(value as u64)]);
+        Table::new(schema, rows.collect()).unwrap()
+    }
+
+    fn query(max_groups: usize) -> GroupBy {
+        GroupBy { key: "key".to_string(), value: "value".to_string(), max_groups }
+    }
+
+    /// Runs the mock prover on the group by circuit, with the rows `sorted` as the sorted rows if given,
+    /// checking that the public instances match [`GroupBy::public_output`] when they are computed honestly.
+    fn mock_group_by(
+        rows: &[(u8, u8)],
+        max_groups: usize,
+        sorted: Option<Vec<[Fr; 2]>>,
+    ) -> Result<(), ScaffoldError> {
+        let table = table(rows);
+        let honest = sorted.is_none();
+        let expected = query(max_groups).public_output(&table);
+        let precircuit = pre_run_rlc_builder_on_inputs(
+            move |builder: &mut GateThreadBuilder<Fr>,
+                  rlp: &RlpChip<Fr>,
+                  table: Table<Fr>,
+                  make_public| {
+                let ctx = builder.main(0);
+                let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+                let table = commit_table(ctx, rlp.gate(), &poseidon, &table, make_public);
+                let query = query(max_groups);
+                let result = match sorted {
+                    Some(sorted) => {
+                        group_sorted(ctx, rlp.range(), &table, &query, sorted, make_public)
+                    }
+                    None => group_by(ctx, rlp.range(), &table, &query, make_public),
+                };
+                let permutation = result.unwrap().permutation;
+                if honest {
+                    let output = make_public[1..].iter().map(|x| *x.value()).collect::<Vec<_>>();
+                    assert_eq!(output, expected.unwrap());
+                }
+                move |ctx_gate: &mut Context<Fr>, ctx_rlc: &mut Context<Fr>, rlp: &RlpChip<Fr>| {
+                    permutation.constrain(ctx_gate, ctx_rlc, rlp.gate(), rlp.rlc())
+                }
+            },
+            table,
+            CircuitConfig { lookup_bits: Some(8), ..Default::default() },
+        );
+        mock(precircuit, &gen_srs(K))
+    }
+
+    #[test]
+    fn test_group_by() {
+        mock_group_by(&[(3, 10), (1, 20), (3, 255), (0, 1), (1, 0), (3, 7)], 4, None).unwrap();
+        // keys at the ends of the range of the column, and a single group filling all slots
+        mock_group_by(&[(255, 255), (0, 255), (255, 1)], 2, None).unwrap();
+        mock_group_by(&[(5, 1), (5, 2), (5, 3)], 1, None).unwrap();
+        mock_group_by(&[], 2, None).unwrap();
+    }
+
+    #[test]
+    fn test_group_by_unsound_witness() {
+        let rows = [(2, 10), (1, 20), (2, 30)];
+        let sorted = |rows: [(u64, u64); 3]| {
+            Some(rows.map(|(key, value)| [Fr::from(key), Fr::from(value)]).to_vec())
+        };
+        mock_group_by(&rows, 2, sorted([(1, 20), (2, 10), (2, 30)])).unwrap();
+        // not sorted, which would split the group of key 2
+        assert!(mock_group_by(&rows, 3, sorted([(2, 10), (1, 20), (2, 30)])).is_err());
+        // sorted, but drops the row (2, 30) for a copy of (2, 10)
+        assert!(mock_group_by(&rows, 2, sorted([(1, 20), (2, 10), (2, 10)])).is_err());
+        // moves 20 from key 1 to key 2, which keeps the multiset of values and of keys but not of rows
+        assert!(mock_group_by(&rows, 2, sorted([(1, 10), (2, 20), (2, 30)])).is_err());
+    }
+
+    #[test]
+    fn test_group_by_too_many_groups() {
+        let rows = [(1, 1), (2, 2), (3, 3)];
+        assert_eq!(
+            query(2).evaluate(&table(&rows)),
+            Err(TableError::TooManyGroups { found: 3, max: 2 })
+        );
+        assert!(mock_group_by(
+            &rows,
+            2,
+            Some(vec![[Fr::from(1); 2], [Fr::from(2); 2], [Fr::from(3); 2]])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_group_by_evaluate() {
+        let table = table(&[(3, 10), (1, 20), (3, 255), (1, 0)]);
+        assert_eq!(
+            query(2).evaluate(&table).unwrap(),
+            [[1, 2, 20], [3, 2, 265]].map(|g| g.map(Fr::from))
+        );
+        let output = query(3).public_output(&table).unwrap();
+        assert_eq!(output, [2, 1, 2, 20, 3, 2, 265, 0, 0, 0].map(Fr::from));
+    }
+}
@@ -0,0 +1,13 @@
+//! Circuits proving the results of SQL queries over a table that was committed to with Poseidon.
+//!
+//! A data owner publishes the commitment of a [`table::Table`], computed natively with [`table::Table::commitment`].
+//! A query circuit loads the private table, recomputes the commitment in the circuit with [`table::commit_table`] and exposes it,
+//! so a verifier who checks the exposed commitment against the published one knows the query ran on the owner's table.
+//!
+//! [`query::select`] then proves the result of a `SELECT ... WHERE` query over the loaded table, [`aggregate::aggregate`] the result of an aggregate query like `SUM`,
+//! and [`group_by::group_by`] the result of a `GROUP BY` query, which also needs the random challenge of the second phase.
+
+pub mod aggregate;
+pub mod group_by;
+pub mod query;
+pub mod table;
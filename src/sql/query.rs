@@ -0,0 +1,453 @@
+//! `SELECT` queries with a `WHERE` clause over a committed table.
+//!
+//! A query is read from JSON, e.g. `SELECT id, amount WHERE customer = 'alice'` is
+//!
+//! ```json
+//! {
+//!     "columns": ["id", "amount"],
+//!     "where": { "column": "customer", "op": "=", "value": "alice" },
+//!     "output": "rows"
+//! }
+//! ```
+//!
+//! `columns` defaults to all columns of the table, `where` is optional, and `output` is `rows` (the default) or `commitment`.
+//! The comparison `op` is one of `=`, `<`, `<=`, `>`, `>=` with a `value`, or `between` with an inclusive range `low` and `high`.
+//! Ordered comparisons are only allowed on `uint` and `bool` columns.
+//!
+//! The circuit cannot depend on how many rows match, so the result is padded with rows of zeros to the number of rows of the table, and the number of matching rows `count` is exposed with it.
+//! After the table commitment, [`select`] exposes the constants of the `WHERE` clause, then `count`, then either all cells of the padded result row by row,
+//! or the Poseidon hash of `count` followed by those cells.
+//! The columns and the comparison are fixed by the verifying key, while the constants are public instances, so the same keys prove the query for any constants.
+use halo2_base::{
+    gates::{GateInstructions, RangeChip, RangeInstructions},
+    utils::{fe_to_biguint, ScalarField},
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use poseidon::PoseidonChip;
+use serde::{Deserialize, Serialize};
+
+use super::table::{
+    poseidon_hash, poseidon_hash_native, AssignedTable, Cell, Schema, Table, TableError, RATE, T,
+};
+
+/// A comparison of the cells of a column with constants.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum Comparison {
+    #[serde(rename = "=")]
+    Eq { value: Cell },
+    #[serde(rename = "<")]
+    Lt { value: Cell },
+    #[serde(rename = "<=")]
+    Le { value: Cell },
+    #[serde(rename = ">")]
+    Gt { value: Cell },
+    #[serde(rename = ">=")]
+    Ge { value: Cell },
+    /// `low <= x && x <= high`
+    #[serde(rename = "between")]
+    Between { low: Cell, high: Cell },
+}
+
+impl Comparison {
+    fn values(&self) -> Vec<&Cell> {
+        match self {
+            Self::Eq { value }
+            | Self::Lt { value }
+            | Self::Le { value }
+            | Self::Gt { value }
+            | Self::Ge { value } => vec![value],
+            Self::Between { low, high } => vec![low, high],
+        }
+    }
+
+    fn is_ordered(&self) -> bool {
+        !matches!(self, Self::Eq { .. })
+    }
+}
+
+/// A `WHERE` clause comparing one column with constants.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Predicate {
+    pub column: String,
+    #[serde(flatten)]
+    pub comparison: Comparison,
+}
+
+impl Predicate {
+    /// Index of the column and the constants of the comparison, encoded according to the type of the column.
+    pub(super) fn resolve<F: ScalarField>(
+        &self,
+        schema: &Schema,
+    ) -> Result<(usize, Vec<F>), TableError> {
+        let index = schema.index_of(&self.column)?;
+        let ty = schema.columns[index].ty;
+        if self.comparison.is_ordered() && ty.bits().is_none() {
+            return Err(TableError::Unordered { column: self.column.clone(), ty });
+        }
+        let constants = self
+            .comparison
+            .values()
+            .into_iter()
+            .map(|value| {
+                ty.parse_cell(value).map_err(|reason| TableError::InvalidConstant {
+                    column: self.column.clone(),
+                    reason,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((index, constants))
+    }
+
+    /// Whether `value` satisfies the comparison with the resolved `constants`.
+    fn eval<F: ScalarField>(&self, value: F, constants: &[F]) -> bool {
+        let value = fe_to_biguint(&value);
+        let constants = constants.iter().map(fe_to_biguint).collect::<Vec<_>>();
+        match self.comparison {
+            Comparison::Eq { .. } => value == constants[0],
+            Comparison::Lt { .. } => value < constants[0],
+            Comparison::Le { .. } => value <= constants[0],
+            Comparison::Gt { .. } => value > constants[0],
+            Comparison::Ge { .. } => value >= constants[0],
+            Comparison::Between { .. } => constants[0] <= value && value <= constants[1],
+        }
+    }
+
+    /// Same as [`Self::eval`] in the circuit, as a bit. `bits` is the bit width of the column.
+    ///
+    /// `RangeInstructions::is_less_than` is only sound if both sides are less than `2^bits`.
+    /// Cells are, by the table commitment, and the constants are range checked here, so comparisons are sound up to `2^bits - 1`.
+    fn assign<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        value: AssignedValue<F>,
+        constants: &[AssignedValue<F>],
+        bits: usize,
+    ) -> AssignedValue<F> {
+        let gate = range.gate();
+        match self.comparison {
+            Comparison::Eq { .. } => gate.is_equal(ctx, value, constants[0]),
+            Comparison::Lt { .. } => range.is_less_than(ctx, value, constants[0], bits),
+            Comparison::Gt { .. } => range.is_less_than(ctx, constants[0], value, bits),
+            Comparison::Le { .. } => {
+                let gt = range.is_less_than(ctx, constants[0], value, bits);
+                gate.not(ctx, gt)
+            }
+            Comparison::Ge { .. } => {
+                let lt = range.is_less_than(ctx, value, constants[0], bits);
+                gate.not(ctx, lt)
+            }
+            Comparison::Between { .. } => {
+                let below = range.is_less_than(ctx, value, constants[0], bits);
+                let above = range.is_less_than(ctx, constants[1], value, bits);
+                let outside = gate.or(ctx, below, above);
+                gate.not(ctx, outside)
+            }
+        }
+    }
+
+    /// Whether each row of `table` satisfies the predicate, natively.
+    pub fn evaluate<F: ScalarField>(&self, table: &Table<F>) -> Result<Vec<bool>, TableError> {
+        let (index, constants) = self.resolve(&table.schema)?;
+        Ok(table.rows.iter().map(|row| self.eval(row[index], &constants)).collect())
+    }
+}
+
+/// Where the result of a [`Select`] goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    /// The cells of the result are public instances
+    #[default]
+    Rows,
+    /// Only a Poseidon commitment to the result is public, for results too large to expose or to keep private
+    Commitment,
+}
+
+/// `SELECT columns WHERE filter`, see the top of this file for the JSON format.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Select {
+    /// Columns of the result, in order. All columns of the table if empty
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default, rename = "where")]
+    pub filter: Option<Predicate>,
+    #[serde(default)]
+    pub output: Output,
+}
+
+impl Select {
+    /// Indices of the columns of the result.
+    fn projection(&self, schema: &Schema) -> Result<Vec<usize>, TableError> {
+        if self.columns.is_empty() {
+            return Ok((0..schema.columns.len()).collect());
+        }
+        self.columns.iter().map(|name| schema.index_of(name)).collect()
+    }
+
+    /// The rows of `table` satisfying the filter, in order, with only the selected columns.
+    pub fn evaluate<F: ScalarField>(&self, table: &Table<F>) -> Result<Vec<Vec<F>>, TableError> {
+        let projection = self.projection(&table.schema)?;
+        let selected = match &self.filter {
+            Some(predicate) => predicate.evaluate(table)?,
+            None => vec![true; table.rows.len()],
+        };
+        Ok(table
+            .rows
+            .iter()
+            .zip(selected)
+            .filter(|(_, selected)| *selected)
+            .map(|(row, _)| projection.iter().map(|&i| row[i]).collect())
+            .collect())
+    }
+
+    /// The public instances [`select`] exposes after the table commitment, computed natively.
+    pub fn public_output<F: ScalarField>(&self, table: &Table<F>) -> Result<Vec<F>, TableError> {
+        let mut output = match &self.filter {
+            Some(predicate) => predicate.resolve(&table.schema)?.1,
+            None => vec![],
+        };
+        let result = self.evaluate(table)?;
+        let count = F::from(result.len() as u64);
+        let padding = (table.rows.len() - result.len()) * self.projection(&table.schema)?.len();
+        let cells = result.into_iter().flatten().chain(vec![F::zero(); padding]);
+        output.push(count);
+        match self.output {
+            Output::Rows => output.extend(cells),
+            Output::Commitment => output
+                .push(poseidon_hash_native(&[count].into_iter().chain(cells).collect::<Vec<_>>())),
+        }
+        Ok(output)
+    }
+}
+
+/// Loads the constants of `predicate`, pushes them to `make_public`, and returns whether each row of `table` satisfies `predicate`, as bits.
+///
+/// `range` must have been created with a positive number of lookup bits, e.g. [`Schema::lookup_bits`], if the comparison is ordered.
+pub fn filter<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    table: &AssignedTable<F>,
+    predicate: &Predicate,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<Vec<AssignedValue<F>>, TableError> {
+    let (index, constants) = predicate.resolve(&table.schema)?;
+    let constants = ctx.assign_witnesses(constants);
+    make_public.extend(constants.iter().copied());
+    // only `uint` and `bool` columns get here with ordered comparisons
+    let bits = table.schema.columns[index].ty.bits().unwrap_or(0);
+    if predicate.comparison.is_ordered() {
+        for &constant in &constants {
+            range.range_check(ctx, constant, bits);
+        }
+    }
+    Ok(table
+        .rows
+        .iter()
+        .map(|row| predicate.assign(ctx, range, row[index], &constants, bits))
+        .collect())
+}
+
+/// The result of [`select`] in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedSelect<F: ScalarField> {
+    /// Number of rows satisfying the filter
+    pub count: AssignedValue<F>,
+    /// The `count` rows of the result, followed by rows of zeros up to the number of rows of the table
+    pub rows: Vec<Vec<AssignedValue<F>>>,
+}
+
+/// Proves the result of `query` over `table` and pushes it to `make_public` as described at the top of this file.
+///
+/// The result is computed from the table by constraints only, so it contains exactly the rows satisfying the filter, in their original order:
+/// each row is moved to the slot given by the number of selected rows before it, so selected rows get distinct slots below `count` and no row can be dropped or added.
+/// This takes a quadratic number of cells in the number of rows.
+pub fn select<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    poseidon: &PoseidonChip<F, T, RATE>,
+    table: &AssignedTable<F>,
+    query: &Select,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<AssignedSelect<F>, TableError> {
+    let gate = range.gate();
+    let projection = query.projection(&table.schema)?;
+    let num_rows = table.rows.len();
+    let selected = match &query.filter {
+        Some(predicate) => filter(ctx, range, table, predicate, make_public)?,
+        None => vec![ctx.load_constant(F::one()); num_rows],
+    };
+
+    // the slot of each row is the number of selected rows before it
+    let mut count = ctx.load_zero();
+    let mut slots = Vec::with_capacity(num_rows);
+    for &bit in &selected {
+        slots.push(count);
+        count = gate.add(ctx, count, bit);
+    }
+    // `moves[i][j]` is 1 if row `i` is selected and moves to slot `j`, which is at most `i`
+    let mut moves = Vec::with_capacity(num_rows);
+    for (i, (&bit, &slot)) in selected.iter().zip(&slots).enumerate() {
+        let mut row_moves = Vec::with_capacity(i + 1);
+        for j in 0..=i {
+            let is_slot = gate.is_equal(ctx, slot, Constant(F::from(j as u64)));
+            row_moves.push(gate.mul(ctx, bit, is_slot));
+        }
+        moves.push(row_moves);
+    }
+    let mut rows = Vec::with_capacity(num_rows);
+    for j in 0..num_rows {
+        let row = projection
+            .iter()
+            .map(|&column| {
+                gate.inner_product(
+                    ctx,
+                    (j..num_rows).map(|i| table.rows[i][column]),
+                    (j..num_rows).map(|i| Existing(moves[i][j])),
+                )
+            })
+            .collect::<Vec<_>>();
+        rows.push(row);
+    }
+
+    make_public.push(count);
+    let cells = rows.iter().flatten().copied();
+    match query.output {
+        Output::Rows => make_public.extend(cells),
+        Output::Commitment => {
+            let inputs = [count].into_iter().chain(cells).collect::<Vec<_>>();
+            make_public.push(poseidon_hash(ctx, gate, poseidon, &inputs));
+        }
+    }
+    Ok(AssignedSelect { count, rows })
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        scaffold::{
+            lookup_bits,
+            testing::{check_against_native, Arbitrary, DifferentialTest},
+            CircuitConfig,
+        },
+        sql::table::{commit_table, Column, ColumnType, R_F, R_P},
+    };
+
+    /// A table with columns `key` and `value`, with keys in `0..4` so that equality filters match some rows.
+    fn table(rows: &[(u8, u8)]) -> Table<Fr> {
+        let column =
+            |name: &str| Column { name: name.to_string(), ty: ColumnType::Uint { bits: 8 } };
+        let schema = Schema { columns: vec![column("key"), column("value")] };
+        let rows =
+            rows.iter().map(|&(key, value)| vec![Fr::from(key as u64 % 4), Fr::from(value as u64)]);
+        Table::new(schema, rows.collect()).unwrap()
+    }
+
+    fn query(key: u8, output: Output) -> Select {
+        let filter = Predicate {
+            column: "key".to_string(),
+            comparison: Comparison::Eq { value: Cell::Number(key as u64 % 4) },
+        };
+        Select { columns: vec!["value".to_string()], filter: Some(filter), output }
+    }
+
+    /// `SELECT key WHERE value op constants`, with the 8-bit values covering the comparisons near `2^8`.
+    fn ordered_query(op: u8, [a, b]: [u8; 2]) -> Select {
+        let [a, b] = [a, b].map(|x| Cell::Number(x as u64));
+        let comparison = match op % 5 {
+            0 => Comparison::Lt { value: a },
+            1 => Comparison::Le { value: a },
+            2 => Comparison::Gt { value: a },
+            3 => Comparison::Ge { value: a },
+            _ => Comparison::Between { low: a, high: b },
+        };
+        let filter = Predicate { column: "value".to_string(), comparison };
+        Select { columns: vec!["key".to_string()], filter: Some(filter), output: Output::Rows }
+    }
+
+    /// Checks [`select`] against [`Select::public_output`] on random tables.
+    fn check_select<Q: Arbitrary>(query: impl Fn(Q) -> Select + Clone) {
+        let native_query = query.clone();
+        let result = check_against_native(
+            move |ctx, (rows, q): (Vec<(u8, u8)>, Q), make_public| {
+                let range = RangeChip::default(lookup_bits());
+                let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+                let table = commit_table(ctx, range.gate(), &poseidon, &table(&rows), make_public);
+                select(ctx, &range, &poseidon, &table, &query(q), make_public).unwrap();
+            },
+            move |(rows, q)| {
+                let table = table(&rows);
+                [vec![table.commitment()], native_query(q).public_output(&table).unwrap()].concat()
+            },
+            DifferentialTest {
+                config: CircuitConfig { lookup_bits: Some(8), ..Default::default() },
+                ..Default::default()
+            },
+        );
+        if let Err(counterexample) = result {
+            panic!("{counterexample}");
+        }
+    }
+
+    #[test]
+    fn test_select_rows() {
+        check_select(|(key, filtered): (u8, bool)| {
+            let mut query = query(key, Output::Rows);
+            if !filtered {
+                query.filter = None;
+            }
+            query
+        });
+    }
+
+    #[test]
+    fn test_select_commitment() {
+        check_select(|key| query(key, Output::Commitment));
+    }
+
+    #[test]
+    fn test_select_ordered() {
+        check_select(|(op, constants)| ordered_query(op, constants));
+    }
+
+    #[test]
+    fn test_select_evaluate() {
+        let table = table(&[(1, 10), (2, 20), (1, 30), (3, 40), (1, 50)]);
+        let result = query(1, Output::Rows).evaluate(&table).unwrap();
+        assert_eq!(result, vec![vec![Fr::from(10)], vec![Fr::from(30)], vec![Fr::from(50)]]);
+
+        let output = query(1, Output::Rows).public_output(&table).unwrap();
+        let expected = [1, 3, 10, 30, 50, 0, 0].map(Fr::from);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_select_near_max() {
+        let table = table(&[(0, 0), (1, 254), (2, 255)]);
+        let keys = |op, constants| ordered_query(op, constants).evaluate(&table).unwrap().concat();
+        assert_eq!(keys(0, [255, 0]), [0, 1].map(Fr::from));
+        assert_eq!(keys(1, [255, 0]), [0, 1, 2].map(Fr::from));
+        assert_eq!(keys(2, [254, 0]), [2].map(Fr::from));
+        assert_eq!(keys(3, [0, 0]), [0, 1, 2].map(Fr::from));
+        assert_eq!(keys(4, [254, 255]), [1, 2].map(Fr::from));
+        assert_eq!(keys(4, [255, 254]), []);
+    }
+
+    #[test]
+    fn test_select_invalid_predicate() {
+        let mut query = ordered_query(0, [0, 0]);
+        query.filter.as_mut().unwrap().comparison = Comparison::Lt { value: Cell::Number(256) };
+        assert!(matches!(query.evaluate(&table(&[])), Err(TableError::InvalidConstant { .. })));
+
+        query.columns.push("missing".to_string());
+        assert_eq!(
+            query.evaluate(&table(&[])),
+            Err(TableError::UnknownColumn("missing".to_string()))
+        );
+    }
+}
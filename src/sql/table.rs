@@ -0,0 +1,561 @@
+//! Typed tables of field elements and their Poseidon commitment.
+//!
+//! A table is read from JSON with a schema and rows of cells, e.g.
+//!
+//! ```json
+//! {
+//!     "schema": [
+//!         { "name": "id", "type": "uint", "bits": 32 },
+//!         { "name": "name", "type": "text" },
+//!         { "name": "active", "type": "bool" }
+//!     ],
+//!     "rows": [
+//!         [1, "alice", true],
+//!         [2, "bob", false]
+//!     ]
+//! }
+//! ```
+//!
+//! Each cell is encoded as one field element according to the type of its column, see [`ColumnType`].
+//! Unsigned integers that do not fit in a JSON number may be given as decimal strings.
+use std::{collections::HashSet, fmt, fs::File, io::BufReader, path::Path};
+
+use halo2_base::{
+    gates::GateChip,
+    utils::{fe_to_biguint, ScalarField},
+    AssignedValue, Context,
+};
+use poseidon::PoseidonChip;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use snark_verifier_sdk::{snark_verifier::util::hash::Poseidon, NativeLoader};
+
+use crate::scaffold::{cmd::Cli, read_inputs_or_keygen_inputs, KeygenInputs, ScaffoldError};
+
+/// Width of the Poseidon state used for table commitments
+pub const T: usize = 3;
+/// Number of field elements absorbed per Poseidon permutation
+pub const RATE: usize = 2;
+/// Number of full rounds of Poseidon
+pub const R_F: usize = 8;
+/// Number of partial rounds of Poseidon
+pub const R_P: usize = 57;
+
+/// Maximum bit width of an unsigned integer column, so that sums and differences of cells stay far from the field modulus.
+pub const MAX_UINT_BITS: usize = 128;
+/// Maximum length in bytes of a text cell, so that it fits in one field element.
+pub const MAX_TEXT_BYTES: usize = 30;
+/// Maximum number of lookup bits chosen by [`Schema::lookup_bits`], so that the lookup table does not dominate the size of circuits over small tables.
+pub const MAX_AUTO_LOOKUP_BITS: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ColumnType {
+    /// Unsigned integers less than `2^bits`, with `bits` at most [`MAX_UINT_BITS`]
+    Uint { bits: usize },
+    /// `true` or `false`, encoded as 1 or 0
+    Bool,
+    /// UTF-8 strings of at most [`MAX_TEXT_BYTES`] bytes, encoded with [`encode_text`]
+    Text,
+    /// Arbitrary field elements given as decimal strings, e.g. hashes
+    Field,
+}
+
+impl ColumnType {
+    /// Number of bits of the cells of this type, if they are integers.
+    pub fn bits(&self) -> Option<usize> {
+        match *self {
+            Self::Uint { bits } => Some(bits),
+            Self::Bool => Some(1),
+            Self::Text | Self::Field => None,
+        }
+    }
+
+    /// Encodes a JSON cell of this type as a field element.
+    pub fn parse_cell<F: ScalarField>(&self, cell: &Cell) -> Result<F, String> {
+        let value = match (self, cell) {
+            (Self::Uint { .. } | Self::Field, Cell::Number(n)) => F::from(*n),
+            (Self::Uint { .. }, Cell::String(s)) => {
+                F::from_u128(s.parse().map_err(|_| format!("`{s}` is not an unsigned integer"))?)
+            }
+            (Self::Bool, Cell::Bool(b)) => F::from(*b as u64),
+            (Self::Text, Cell::String(s)) => encode_text(s)?,
+            (Self::Field, Cell::String(s)) => {
+                F::from_str_vartime(s).ok_or_else(|| format!("`{s}` is not a field element"))?
+            }
+            (ty, cell) => return Err(format!("{cell} is not a {ty}")),
+        };
+        self.check(&value)?;
+        Ok(value)
+    }
+
+    /// Checks that `value` is the encoding of a cell of this type. Text and field cells may be any field element.
+    fn check<F: ScalarField>(&self, value: &F) -> Result<(), String> {
+        match self.bits() {
+            Some(bits) if fe_to_biguint(value).bits() as usize > bits => {
+                Err(format!("{} does not fit in {bits} bits", fe_to_biguint(value)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The type as `[tag, bits]`, absorbed into the commitment so that it also binds the schema.
+    fn encode(&self) -> [u64; 2] {
+        match *self {
+            Self::Uint { bits } => [0, bits as u64],
+            Self::Bool => [1, 1],
+            Self::Text => [2, 0],
+            Self::Field => [3, 0],
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uint { bits } => write!(f, "uint{bits}"),
+            Self::Bool => write!(f, "bool"),
+            Self::Text => write!(f, "text"),
+            Self::Field => write!(f, "field"),
+        }
+    }
+}
+
+/// Encodes a string as the big-endian integer of its UTF-8 bytes after a leading 1 byte, so that strings differing only by leading zero bytes stay distinct.
+pub fn encode_text<F: ScalarField>(s: &str) -> Result<F, String> {
+    if s.len() > MAX_TEXT_BYTES {
+        return Err(format!("`{s}` is longer than {MAX_TEXT_BYTES} bytes"));
+    }
+    Ok(s.bytes().fold(F::one(), |acc, byte| acc * F::from(256) + F::from(byte as u64)))
+}
+
+/// A cell of a table as written in JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Cell {
+    Bool(bool),
+    Number(u64),
+    String(String),
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "\"{s}\""),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    #[serde(flatten)]
+    pub ty: ColumnType,
+}
+
+/// The columns of a table, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+}
+
+impl Schema {
+    /// Index of the column called `name`.
+    pub fn index_of(&self, name: &str) -> Result<usize, TableError> {
+        self.columns
+            .iter()
+            .position(|column| column.name == name)
+            .ok_or_else(|| TableError::UnknownColumn(name.to_string()))
+    }
+
+    /// Largest bit width of the integer columns, or 0 if there are none.
+    pub fn max_bits(&self) -> usize {
+        self.columns.iter().filter_map(|column| column.ty.bits()).max().unwrap_or(0)
+    }
+
+    /// Number of lookup bits for the range checks of comparisons between cells of this schema, for `--lookup-bits`.
+    ///
+    /// Comparing `bits`-bit numbers range checks `ceil(bits / lookup_bits) + 1` limbs of `lookup_bits` bits, so the widest column is split into
+    /// as few limbs of at most [`MAX_AUTO_LOOKUP_BITS`] bits as possible, all of the same size so that no bits of the limbs are wasted on padding.
+    pub fn lookup_bits(&self) -> usize {
+        let max_bits = self.max_bits().max(1);
+        let num_limbs = (max_bits + MAX_AUTO_LOOKUP_BITS - 1) / MAX_AUTO_LOOKUP_BITS;
+        (max_bits + num_limbs - 1) / num_limbs
+    }
+
+    fn validate(&self) -> Result<(), TableError> {
+        let mut names = HashSet::new();
+        for column in &self.columns {
+            if !names.insert(&column.name) {
+                return Err(TableError::InvalidSchema(format!(
+                    "column `{}` is declared twice",
+                    column.name
+                )));
+            }
+            if let ColumnType::Uint { bits } = column.ty {
+                if bits == 0 || bits > MAX_UINT_BITS {
+                    return Err(TableError::InvalidSchema(format!(
+                        "column `{}` has {bits} bits, but unsigned integers must have 1 to {MAX_UINT_BITS} bits",
+                        column.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets `--lookup-bits` to [`Schema::lookup_bits`] of the table in the inputs of `cli`, unless it was given.
+/// If the inputs cannot be read, `cli` is left unchanged so that the error is reported by [`crate::scaffold::run`].
+pub fn set_lookup_bits<T: KeygenInputs>(cli: &mut Cli, schema: impl FnOnce(&T) -> &Schema) {
+    if cli.lookup_bits.is_none() {
+        if let Ok(inputs) = read_inputs_or_keygen_inputs::<T>(cli) {
+            cli.lookup_bits = Some(schema(&inputs).lookup_bits());
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableError {
+    InvalidSchema(String),
+    UnknownColumn(String),
+    /// A row does not have one cell per column
+    RowLength {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A cell is not a valid value of the type of its column
+    InvalidCell {
+        row: usize,
+        column: String,
+        reason: String,
+    },
+    /// A constant of a query is not a valid value of the type of the column it is compared with
+    InvalidConstant {
+        column: String,
+        reason: String,
+    },
+    /// An ordered comparison or a `GROUP BY` on a column that is not an unsigned integer or boolean
+    Unordered {
+        column: String,
+        ty: ColumnType,
+    },
+    /// An aggregate other than `COUNT` on a column that is not an unsigned integer or boolean
+    NotNumeric {
+        column: String,
+        ty: ColumnType,
+    },
+    /// An aggregate other than `COUNT` without a column
+    MissingColumn,
+    /// A `GROUP BY` whose key and value cells cannot be packed together into one field element
+    GroupTooWide {
+        key: String,
+        value: String,
+    },
+    /// A `GROUP BY` with more distinct keys than its `max_groups`
+    TooManyGroups {
+        found: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSchema(reason) => write!(f, "invalid schema: {reason}"),
+            Self::UnknownColumn(name) => write!(f, "unknown column `{name}`"),
+            Self::RowLength { row, expected, found } => {
+                write!(f, "row {row} has {found} cells, but the schema has {expected} columns")
+            }
+            Self::InvalidCell { row, column, reason } => {
+                write!(f, "row {row}, column `{column}`: {reason}")
+            }
+            Self::InvalidConstant { column, reason } => {
+                write!(f, "constant compared with column `{column}`: {reason}")
+            }
+            Self::Unordered { column, ty } => {
+                write!(f, "column `{column}` of type {ty} cannot be compared with <, <=, >, >= or between, or grouped by")
+            }
+            Self::NotNumeric { column, ty } => {
+                write!(f, "column `{column}` of type {ty} cannot be aggregated")
+            }
+            Self::MissingColumn => write!(f, "only count can be computed without a column"),
+            Self::GroupTooWide { key, value } => {
+                write!(f, "the widths of key `{key}` and value `{value}` add up to more bits than a field element holds")
+            }
+            Self::TooManyGroups { found, max } => {
+                write!(f, "found {found} distinct keys, but at most {max} groups are allowed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+/// A table with a schema, and rows of cells encoded as field elements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table<F: ScalarField> {
+    pub schema: Schema,
+    /// Rows of one cell per column of the schema
+    pub rows: Vec<Vec<F>>,
+}
+
+impl<F: ScalarField> Table<F> {
+    /// Checks that the schema is valid and that every row has one cell of the right type per column.
+    pub fn new(schema: Schema, rows: Vec<Vec<F>>) -> Result<Self, TableError> {
+        schema.validate()?;
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != schema.columns.len() {
+                return Err(TableError::RowLength {
+                    row: i,
+                    expected: schema.columns.len(),
+                    found: row.len(),
+                });
+            }
+            for (column, value) in schema.columns.iter().zip(row) {
+                column.ty.check(value).map_err(|reason| TableError::InvalidCell {
+                    row: i,
+                    column: column.name.clone(),
+                    reason,
+                })?;
+            }
+        }
+        Ok(Self { schema, rows })
+    }
+
+    /// A table of `num_rows` rows of zeros (empty strings for text columns), for keygen.
+    /// A circuit created with it has the same constraints as with any other table with the same schema and number of rows.
+    pub fn placeholder(schema: Schema, num_rows: usize) -> Self {
+        let row = schema
+            .columns
+            .iter()
+            .map(|column| match column.ty {
+                ColumnType::Text => F::one(),
+                _ => F::zero(),
+            })
+            .collect::<Vec<_>>();
+        Self { schema, rows: vec![row; num_rows] }
+    }
+
+    /// Reads a table from a JSON file, see the top of this file for the format.
+    pub fn read(path: &Path) -> Result<Self, ScaffoldError> {
+        let f = File::open(path)
+            .map_err(|source| ScaffoldError::MissingInput { path: path.to_owned(), source })?;
+        serde_json::from_reader(BufReader::new(f))
+            .map_err(|source| ScaffoldError::InvalidInput { path: path.to_owned(), source })
+    }
+
+    /// Cells of the column at `index`, in row order.
+    pub fn column(&self, index: usize) -> Vec<F> {
+        self.rows.iter().map(|row| row[index]).collect()
+    }
+
+    /// The number of rows and columns and the type of each column, absorbed before the cells.
+    fn header(&self) -> Vec<F> {
+        [self.rows.len() as u64, self.schema.columns.len() as u64]
+            .into_iter()
+            .chain(self.schema.columns.iter().flat_map(|column| column.ty.encode()))
+            .map(F::from)
+            .collect()
+    }
+
+    /// Poseidon commitment to the table, computed natively so that the data owner can publish it.
+    /// This is the value [`commit_table`] exposes in the circuit.
+    pub fn commitment(&self) -> F {
+        let cells = self.rows.iter().flatten().copied();
+        poseidon_hash_native(&self.header().into_iter().chain(cells).collect::<Vec<_>>())
+    }
+}
+
+/// Poseidon hash of `inputs`, computed natively with [`R_F`] and [`R_P`]. This is the value [`poseidon_hash`] computes in the circuit.
+pub fn poseidon_hash_native<F: ScalarField>(inputs: &[F]) -> F {
+    let mut poseidon = Poseidon::<F, F, T, RATE>::new(&NativeLoader, R_F, R_P);
+    poseidon.update(inputs);
+    poseidon.squeeze()
+}
+
+/// Poseidon hash of `inputs` in the circuit.
+///
+/// `poseidon` must have been created with [`R_F`] and [`R_P`] and not have absorbed anything yet. It is cloned, so it can be reused afterwards.
+pub fn poseidon_hash<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    poseidon: &PoseidonChip<F, T, RATE>,
+    inputs: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let mut poseidon = poseidon.clone();
+    poseidon.update(inputs);
+    poseidon.squeeze(ctx, gate).unwrap()
+}
+
+#[derive(Deserialize)]
+struct TableJson {
+    schema: Schema,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl<'de, F: ScalarField> Deserialize<'de> for Table<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let TableJson { schema, rows } = TableJson::deserialize(deserializer)?;
+        // the cells are parsed with the types of the schema, so the schema and the row lengths are checked before `Table::new` does
+        schema.validate().map_err(de::Error::custom)?;
+        let rows = rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                if row.len() != schema.columns.len() {
+                    return Err(TableError::RowLength {
+                        row: i,
+                        expected: schema.columns.len(),
+                        found: row.len(),
+                    });
+                }
+                row.iter()
+                    .zip(&schema.columns)
+                    .map(|(cell, column)| {
+                        column.ty.parse_cell(cell).map_err(|reason| TableError::InvalidCell {
+                            row: i,
+                            column: column.name.clone(),
+                            reason,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(de::Error::custom)?;
+        Self::new(schema, rows).map_err(de::Error::custom)
+    }
+}
+
+/// A [`Table`] loaded into a circuit, with one witness per cell.
+#[derive(Clone, Debug)]
+pub struct AssignedTable<F: ScalarField> {
+    pub schema: Schema,
+    pub rows: Vec<Vec<AssignedValue<F>>>,
+    /// Poseidon commitment to the table, equal to [`Table::commitment`]
+    pub commitment: AssignedValue<F>,
+}
+
+impl<F: ScalarField> AssignedTable<F> {
+    /// Cells of the column at `index`, in row order.
+    pub fn column(&self, index: usize) -> Vec<AssignedValue<F>> {
+        self.rows.iter().map(|row| row[index]).collect()
+    }
+}
+
+/// Loads `table` into the circuit, commits to it in the same order as [`Table::commitment`] and pushes the commitment to `make_public`.
+/// The number of rows and columns and the type of each column are absorbed first, then the cells row by row.
+///
+/// The header is loaded as constants, so the dimensions and schema of the table are fixed by the verifying key, while the cells are private.
+/// Cells are not range checked: the data owner only publishes the commitment of a valid table, and the prover cannot find another table with the same commitment.
+/// `poseidon` must be fresh, as for [`poseidon_hash`].
+pub fn commit_table<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    poseidon: &PoseidonChip<F, T, RATE>,
+    table: &Table<F>,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> AssignedTable<F> {
+    let header = table.header().into_iter().map(|x| ctx.load_constant(x)).collect::<Vec<_>>();
+    let rows =
+        table.rows.iter().map(|row| ctx.assign_witnesses(row.iter().copied())).collect::<Vec<_>>();
+
+    let cells = rows.iter().flatten().copied();
+    let commitment =
+        poseidon_hash(ctx, gate, poseidon, &header.into_iter().chain(cells).collect::<Vec<_>>());
+    make_public.push(commitment);
+    AssignedTable { schema: table.schema.clone(), rows, commitment }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::scaffold::testing::{check_against_native, DifferentialTest};
+
+    fn schema() -> Schema {
+        Schema {
+            columns: vec![
+                Column { name: "amount".to_string(), ty: ColumnType::Uint { bits: 64 } },
+                Column { name: "paid".to_string(), ty: ColumnType::Bool },
+            ],
+        }
+    }
+
+    fn table(rows: Vec<(u64, bool)>) -> Table<Fr> {
+        let rows =
+            rows.into_iter().map(|(amount, paid)| vec![Fr::from(amount), Fr::from(paid as u64)]);
+        Table::new(schema(), rows.collect()).unwrap()
+    }
+
+    #[test]
+    fn test_commit_table() {
+        let result = check_against_native(
+            |ctx, rows, make_public| {
+                let gate = GateChip::default();
+                let poseidon = PoseidonChip::new(ctx, R_F, R_P).unwrap();
+                commit_table(ctx, &gate, &poseidon, &table(rows), make_public);
+            },
+            |rows| vec![table(rows).commitment()],
+            DifferentialTest::default(),
+        );
+        if let Err(counterexample) = result {
+            panic!("{counterexample}");
+        }
+    }
+
+    #[test]
+    fn test_commitment_binds_schema() {
+        let rows = vec![vec![Fr::from(1), Fr::from(0)]];
+        let mut other = schema();
+        other.columns[0].ty = ColumnType::Uint { bits: 32 };
+        assert_ne!(
+            Table::new(schema(), rows.clone()).unwrap().commitment(),
+            Table::new(other, rows).unwrap().commitment()
+        );
+    }
+
+    #[test]
+    fn test_table_json() {
+        let json = r#"{
+            "schema": [
+                { "name": "id", "type": "uint", "bits": 8 },
+                { "name": "name", "type": "text" },
+                { "name": "active", "type": "bool" }
+            ],
+            "rows": [[1, "ab", true], ["255", "", false]]
+        }"#;
+        let table: Table<Fr> = serde_json::from_str(json).unwrap();
+        assert_eq!(table.schema.index_of("active"), Ok(2));
+        assert_eq!(table.column(0), vec![Fr::from(1), Fr::from(255)]);
+        assert_eq!(table.column(1), vec![Fr::from(0x016162), Fr::from(1)]);
+        assert_eq!(table.column(2), vec![Fr::from(1), Fr::from(0)]);
+
+        let too_wide = json.replace("\"255\"", "256");
+        assert!(serde_json::from_str::<Table<Fr>>(&too_wide).is_err());
+        // a row with an extra cell is rejected rather than truncated to the schema
+        let extra_cell = json.replace("[1, \"ab\", true]", "[1, \"ab\", true, 5]");
+        let err = serde_json::from_str::<Table<Fr>>(&extra_cell).unwrap_err();
+        assert!(err.to_string().contains("row 0 has 4 cells"));
+        let zero_bits = json.replace("\"bits\": 8", "\"bits\": 0");
+        let err = serde_json::from_str::<Table<Fr>>(&zero_bits).unwrap_err();
+        assert!(err.to_string().contains("unsigned integers must have 1 to"));
+        let wrong_type = json.replace("true", "1");
+        assert!(serde_json::from_str::<Table<Fr>>(&wrong_type).is_err());
+    }
+
+    #[test]
+    fn test_lookup_bits() {
+        // 64 bits are split into 6 limbs of 11 bits rather than 5 limbs of 12 bits and one of 4
+        assert_eq!(schema().lookup_bits(), 11);
+        let mut schema = schema();
+        schema.columns[0].ty = ColumnType::Uint { bits: 8 };
+        assert_eq!(schema.lookup_bits(), 8);
+        schema.columns[0].ty = ColumnType::Text;
+        assert_eq!(schema.lookup_bits(), 1);
+    }
+}